@@ -0,0 +1,71 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use glam::Vec3;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rtrace::geometry::mesh::{Mesh, Triangle};
+use rtrace::geometry::ray::Ray;
+use rtrace::geometry::surface::Surface;
+use rtrace::geometry::triangle_packet::TrianglePacket;
+use rtrace::geometry::watertight::Shear;
+
+fn get_triangles(rng: &mut StdRng, count: usize) -> Vec<Triangle> {
+    (0..count)
+        .map(|_| {
+            let center = Vec3::new(rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0));
+            let mut vertex = || center + Vec3::new(rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5), rng.gen_range(-0.5..0.5));
+            Triangle::new(vertex(), vertex(), vertex())
+        })
+        .collect()
+}
+
+fn get_rays(rng: &mut StdRng, count: usize) -> Vec<Ray> {
+    (0..count)
+        .map(|_| {
+            let target = Vec3::new(rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0));
+            let position = Vec3::new(0.0, -30.0, 0.0);
+            Ray::new(position, (target - position).normalize(), 550.0, 1.0)
+        })
+        .collect()
+}
+
+fn triangles(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(1);
+    let triangles = get_triangles(&mut rng, 1024);
+    let vertices: Vec<[Vec3; 3]> = triangles.iter().map(|t| t.get_vertices()).collect();
+    let packets = TrianglePacket::pack(&vertices);
+    let rays = get_rays(&mut rng, 64);
+
+    let mut group = c.benchmark_group("1024 triangles");
+    group.bench_function("scalar Triangle::intersect", |b| b.iter(|| {
+        rays.iter()
+            .filter_map(|ray| triangles.iter().filter_map(|t| t.intersect(black_box(ray))).map(|i| i.t).reduce(f32::min))
+            .sum::<f32>()
+    }));
+    group.bench_function("packets scalar", |b| b.iter(|| {
+        rays.iter()
+            .filter_map(|ray| packets.iter().fold(None, |t_max: Option<f32>, p| {
+                p.intersect_scalar(black_box(ray), &Shear::new(ray.direction), t_max.unwrap_or(f32::INFINITY)).map(|h| h.distance).or(t_max)
+            }))
+            .sum::<f32>()
+    }));
+    group.bench_function("packets simd", |b| b.iter(|| {
+        rays.iter()
+            .filter_map(|ray| packets.iter().fold(None, |t_max: Option<f32>, p| {
+                p.intersect(black_box(ray), &Shear::new(ray.direction), t_max.unwrap_or(f32::INFINITY)).map(|h| h.distance).or(t_max)
+            }))
+            .sum::<f32>()
+    }));
+    group.finish();
+}
+
+fn mesh(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(2);
+    let mesh = Mesh::new(get_triangles(&mut rng, 100_000));
+    let rays = get_rays(&mut rng, 1024);
+    c.bench_function("mesh of 100000 triangles", |b| b.iter(|| {
+        rays.iter().filter_map(|ray| mesh.intersect(black_box(ray))).count()
+    }));
+}
+
+criterion_group!(benches, triangles, mesh);
+criterion_main!(benches);
@@ -0,0 +1,78 @@
+use glam::{Mat3, Quat, Vec3};
+use crate::geometry::ray::Ray;
+
+/// Maps screen coordinates in `[-1, 1]` to a ray leaving the camera. In the camera's own frame
+/// x points to the right of the image, y forward and z towards the top of the image.
+/// Rays with a strength of zero fall outside of the projection.
+pub trait Camera: Sync + Send {
+    fn get_ray(&self, x: f32, y: f32, wavelength: f32) -> Ray;
+
+    /// Ray through the center of the lens, used to find the focus point. Cameras without
+    /// adjustable focus return `None`.
+    fn get_focus_ray(&self, _x: f32, _y: f32) -> Option<Ray> {
+        None
+    }
+
+    /// Moves the plane of focus through `point`
+    fn set_focus(&mut self, _point: Vec3) {
+    }
+
+    /// Width divided by height of the rendered image
+    fn set_aspect_ratio(&mut self, _aspect_ratio: f32) {
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum FieldOfView {
+    /// Angle between the left and right edge of the image, in radians
+    Horizontal(f32),
+    /// Angle between the top and bottom edge of the image, in radians
+    Vertical(f32)
+}
+
+impl FieldOfView {
+
+    /// Half extents of the image plane at distance 1 for the given aspect ratio
+    pub fn get_screen_size(&self, aspect_ratio: f32) -> (f32, f32) {
+        match self {
+            FieldOfView::Horizontal(angle) => {
+                let x = (angle * 0.5).tan();
+                (x, x / aspect_ratio)
+            },
+            FieldOfView::Vertical(angle) => {
+                let y = (angle * 0.5).tan();
+                (y * aspect_ratio, y)
+            }
+        }
+    }
+}
+
+/// Orientation of a camera at `eye` looking at `target`, with `up` pointing towards the top of the image
+pub fn get_look_at_orientation(eye: Vec3, target: Vec3, up: Vec3) -> Quat {
+    let forward = (target - eye).normalize();
+    let right = forward.cross(up).normalize();
+    let top = right.cross(forward);
+    Quat::from_mat3(&Mat3::from_cols(right, forward, top))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::camera::camera::{FieldOfView, get_look_at_orientation};
+
+    #[test]
+    fn look_at_orientation() {
+        let q = get_look_at_orientation(Vec3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 2.0, 10.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(q.mul_vec3(Vec3::new(0.0, 1.0, 0.0)).distance(Vec3::new(0.0, 0.0, 1.0)) < 1.0e-5);
+        assert!(q.mul_vec3(Vec3::new(0.0, 0.0, 1.0)).distance(Vec3::new(0.0, 1.0, 0.0)) < 1.0e-5);
+        assert!(q.mul_vec3(Vec3::new(1.0, 0.0, 0.0)).distance(Vec3::new(-1.0, 0.0, 0.0)) < 1.0e-5);
+    }
+
+    #[test]
+    fn screen_size() {
+        let (x, y) = FieldOfView::Horizontal(std::f32::consts::FRAC_PI_2).get_screen_size(2.0);
+        assert!((x - 1.0).abs() < 1.0e-5 && (y - 0.5).abs() < 1.0e-5);
+        let (x, y) = FieldOfView::Vertical(std::f32::consts::FRAC_PI_2).get_screen_size(2.0);
+        assert!((x - 2.0).abs() < 1.0e-5 && (y - 1.0).abs() < 1.0e-5);
+    }
+}
@@ -0,0 +1,50 @@
+use glam::{Quat, Vec3};
+use crate::camera::camera::{Camera, get_look_at_orientation};
+use crate::geometry::ray::Ray;
+
+/// Full 360° panorama, x maps to longitude and y to latitude
+pub struct EquirectangularCamera {
+    pub position: Vec3,
+    pub orientation: Quat
+}
+
+impl EquirectangularCamera {
+    pub fn new(position: Vec3, orientation: Quat) -> EquirectangularCamera {
+        EquirectangularCamera { position, orientation }
+    }
+
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> EquirectangularCamera {
+        EquirectangularCamera::new(eye, get_look_at_orientation(eye, target, up))
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn get_ray(&self, x: f32, y: f32, wavelength: f32) -> Ray {
+        let longitude = x * std::f32::consts::PI;
+        let latitude = y * std::f32::consts::FRAC_PI_2;
+        let direction = Vec3::new(latitude.cos() * longitude.sin(), latitude.cos() * longitude.cos(), -latitude.sin());
+        Ray::new(self.position, self.orientation.mul_vec3(direction), wavelength, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::camera::camera::Camera;
+    use crate::camera::equirectangular::EquirectangularCamera;
+
+    #[test]
+    fn covers_the_full_sphere() {
+        let camera = EquirectangularCamera::look_at(Vec3::ONE, Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0));
+        let direction = |x: f32, y: f32| camera.get_ray(x, y, 550.0).direction;
+        assert_eq!(Vec3::ONE, camera.get_ray(0.7, -0.3, 550.0).position);
+        assert!(direction(0.0, 0.0).distance(Vec3::new(0.0, -1.0, 0.0)) < 1.0e-5);
+        assert!(direction(0.5, 0.0).distance(Vec3::new(-1.0, 0.0, 0.0)) < 1.0e-5);
+        assert!(direction(-0.5, 0.0).distance(Vec3::new(1.0, 0.0, 0.0)) < 1.0e-5);
+        // Both vertical edges look backwards and the horizontal edges at the poles
+        assert!(direction(1.0, 0.0).distance(Vec3::new(0.0, 1.0, 0.0)) < 1.0e-5);
+        assert!(direction(-1.0, 0.0).distance(Vec3::new(0.0, 1.0, 0.0)) < 1.0e-5);
+        assert!(direction(1.0, -1.0).distance(Vec3::new(0.0, 0.0, 1.0)) < 1.0e-5);
+        assert!(direction(-1.0, 1.0).distance(Vec3::new(0.0, 0.0, -1.0)) < 1.0e-5);
+    }
+}
@@ -0,0 +1,93 @@
+use glam::{Quat, Vec3};
+use crate::camera::camera::{Camera, get_look_at_orientation};
+use crate::geometry::ray::Ray;
+
+pub enum FisheyeProjection {
+    /// Distance from the image center is proportional to the angle
+    Equidistant,
+    /// Preserves solid angles, r = 2f * sin(theta / 2)
+    Equisolid
+}
+
+/// Circular fisheye, the image circle touches the shorter edges of the screen
+pub struct FisheyeCamera {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub field_of_view: f32,
+    pub projection: FisheyeProjection,
+    pub aspect_ratio: f32
+}
+
+impl FisheyeCamera {
+    pub fn new(position: Vec3, orientation: Quat, field_of_view: f32, projection: FisheyeProjection) -> FisheyeCamera {
+        FisheyeCamera { position, orientation, field_of_view, projection, aspect_ratio: 1.0 }
+    }
+
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3, field_of_view: f32, projection: FisheyeProjection) -> FisheyeCamera {
+        FisheyeCamera::new(eye, get_look_at_orientation(eye, target, up), field_of_view, projection)
+    }
+
+    fn get_angle(&self, radius: f32) -> f32 {
+        let max_angle = self.field_of_view * 0.5;
+        match self.projection {
+            FisheyeProjection::Equidistant => radius * max_angle,
+            FisheyeProjection::Equisolid => 2.0 * (radius * (max_angle * 0.5).sin()).asin()
+        }
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn get_ray(&self, x: f32, y: f32, wavelength: f32) -> Ray {
+        let x = x * self.aspect_ratio.max(1.0);
+        let y = y / self.aspect_ratio.min(1.0);
+        let radius = (x * x + y * y).sqrt();
+        let strength = if radius > 1.0 { 0.0 } else { 1.0 };
+        let theta = self.get_angle(radius.min(1.0));
+        let phi = y.atan2(x);
+        let direction = Vec3::new(theta.sin() * phi.cos(), theta.cos(), -theta.sin() * phi.sin());
+        Ray::new(self.position, self.orientation.mul_vec3(direction), wavelength, strength)
+    }
+
+    fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.aspect_ratio = aspect_ratio;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::camera::camera::Camera;
+    use crate::camera::fisheye::{FisheyeCamera, FisheyeProjection};
+
+    fn get_camera(projection: FisheyeProjection) -> FisheyeCamera {
+        let eye = Vec3::new(0.0, 0.0, 2.0);
+        FisheyeCamera::look_at(eye, Vec3::new(0.0, 1.0, 2.0), Vec3::new(0.0, 0.0, 1.0), std::f32::consts::PI, projection)
+    }
+
+    #[test]
+    fn edges_of_the_image_circle_see_sideways() {
+        let camera = get_camera(FisheyeProjection::Equidistant);
+        let center = camera.get_ray(0.0, 0.0, 550.0);
+        assert_eq!(Vec3::new(0.0, 0.0, 2.0), center.position);
+        assert!(center.direction.distance(Vec3::new(0.0, 1.0, 0.0)) < 1.0e-5);
+        assert!(camera.get_ray(1.0, 0.0, 550.0).direction.distance(Vec3::new(1.0, 0.0, 0.0)) < 1.0e-5);
+        assert!(camera.get_ray(0.0, -1.0, 550.0).direction.distance(Vec3::new(0.0, 0.0, 1.0)) < 1.0e-5);
+        let corner = camera.get_ray(1.0, -1.0, 550.0);
+        assert_eq!(0.0, corner.strength);
+        assert_eq!(Vec3::new(0.0, 0.0, 2.0), corner.position);
+    }
+
+    #[test]
+    fn projections_map_radius_to_angle() {
+        let angle = |camera: &FisheyeCamera| camera.get_ray(0.5, 0.0, 550.0).direction.angle_between(Vec3::new(0.0, 1.0, 0.0));
+        assert!((angle(&get_camera(FisheyeProjection::Equidistant)) - std::f32::consts::FRAC_PI_4).abs() < 1.0e-5);
+        // 2 asin(0.5 sin 45°)
+        assert!((angle(&get_camera(FisheyeProjection::Equisolid)) - 0.722734).abs() < 1.0e-5);
+
+        let mut wide = get_camera(FisheyeProjection::Equisolid);
+        wide.set_aspect_ratio(2.0);
+        assert!(wide.get_ray(0.5, 0.0, 550.0).direction.distance(Vec3::new(1.0, 0.0, 0.0)) < 1.0e-5);
+        assert_eq!(0.0, wide.get_ray(0.6, 0.0, 550.0).strength);
+        assert!(wide.get_ray(0.0, 1.0, 550.0).direction.distance(Vec3::new(0.0, 0.0, -1.0)) < 1.0e-5);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod camera;
+pub mod perspective;
+pub mod orthographic;
+pub mod fisheye;
+pub mod equirectangular;
+pub mod realistic;
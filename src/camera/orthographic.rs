@@ -0,0 +1,59 @@
+use glam::{Quat, Vec3};
+use crate::camera::camera::{Camera, get_look_at_orientation};
+use crate::geometry::ray::Ray;
+
+/// Parallel projection, `width` is the horizontal extent of the visible area in scene units.
+pub struct OrthographicCamera {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub width: f32,
+    pub aspect_ratio: f32
+}
+
+impl OrthographicCamera {
+    pub fn new(position: Vec3, orientation: Quat, width: f32) -> OrthographicCamera {
+        OrthographicCamera { position, orientation, width, aspect_ratio: 1.0 }
+    }
+
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3, width: f32) -> OrthographicCamera {
+        OrthographicCamera::new(eye, get_look_at_orientation(eye, target, up), width)
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn get_ray(&self, x: f32, y: f32, wavelength: f32) -> Ray {
+        let offset = Vec3::new(x * self.width * 0.5, 0.0, -y * self.width * 0.5 / self.aspect_ratio);
+        Ray::new(
+            self.position + self.orientation.mul_vec3(offset),
+            self.orientation.mul_vec3(Vec3::new(0.0, 1.0, 0.0)),
+            wavelength,
+            1.0
+        )
+    }
+
+    fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.aspect_ratio = aspect_ratio;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::camera::camera::Camera;
+    use crate::camera::orthographic::OrthographicCamera;
+
+    #[test]
+    fn parallel_rays_from_the_image_plane() {
+        let mut camera = OrthographicCamera::look_at(Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 5.0, 0.0), Vec3::new(0.0, 0.0, 1.0), 4.0);
+        camera.set_aspect_ratio(2.0);
+        let center = camera.get_ray(0.0, 0.0, 550.0);
+        assert!(center.position.distance(Vec3::new(1.0, 0.0, 0.0)) < 1.0e-5);
+        assert!(center.direction.distance(Vec3::new(0.0, 1.0, 0.0)) < 1.0e-5);
+        let top_right = camera.get_ray(1.0, -1.0, 550.0);
+        assert!(top_right.position.distance(Vec3::new(3.0, 0.0, 1.0)) < 1.0e-5);
+        assert!(top_right.direction.distance(center.direction) < 1.0e-5);
+        let bottom_left = camera.get_ray(-1.0, 1.0, 550.0);
+        assert!(bottom_left.position.distance(Vec3::new(-1.0, 0.0, -1.0)) < 1.0e-5);
+        assert!(bottom_left.direction.distance(center.direction) < 1.0e-5);
+    }
+}
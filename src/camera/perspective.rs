@@ -0,0 +1,178 @@
+use glam::{Quat, Vec2, Vec3};
+use crate::camera::camera::{Camera, FieldOfView, get_look_at_orientation};
+use crate::geometry::ray::Ray;
+use crate::ptrandom;
+
+pub enum Aperture {
+    Pinhole,
+    Disk,
+    /// Regular polygon formed by `blades` aperture blades, rotated by `rotation` radians
+    Polygon { blades: u32, rotation: f32 }
+}
+
+/// Pinhole or thin lens camera. Lengths of the lens are given in scene units.
+pub struct PerspectiveCamera {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub field_of_view: FieldOfView,
+    pub aspect_ratio: f32,
+    pub focal_distance: f32,
+    pub focal_length: f32,
+    pub f_number: f32,
+    pub aperture: Aperture,
+    pub chromatic_aberration: f32
+}
+
+impl PerspectiveCamera {
+
+    pub fn new(position: Vec3, orientation: Quat, field_of_view: FieldOfView) -> PerspectiveCamera {
+        PerspectiveCamera {
+            position,
+            orientation,
+            field_of_view,
+            aspect_ratio: 1.0,
+            focal_distance: 1.0,
+            focal_length: 0.0,
+            f_number: f32::INFINITY,
+            aperture: Aperture::Pinhole,
+            chromatic_aberration: 0.0
+        }
+    }
+
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3, field_of_view: FieldOfView) -> PerspectiveCamera {
+        PerspectiveCamera::new(eye, get_look_at_orientation(eye, target, up), field_of_view)
+    }
+
+    /// Turns the pinhole into a thin lens with a circular aperture of diameter `focal_length / f_number`
+    pub fn with_lens(mut self, focal_length: f32, f_number: f32, focal_distance: f32) -> PerspectiveCamera {
+        self.focal_length = focal_length;
+        self.f_number = f_number;
+        self.focal_distance = focal_distance;
+        if let Aperture::Pinhole = self.aperture {
+            self.aperture = Aperture::Disk;
+        }
+        self
+    }
+
+    pub fn with_aperture(mut self, aperture: Aperture) -> PerspectiveCamera {
+        self.aperture = aperture;
+        self
+    }
+
+    pub fn with_chromatic_aberration(mut self, chromatic_aberration: f32) -> PerspectiveCamera {
+        self.chromatic_aberration = chromatic_aberration;
+        self
+    }
+
+    pub fn get_lens_radius(&self) -> f32 {
+        self.focal_length / (2.0 * self.f_number)
+    }
+
+    /// Uniformly samples a point on the aperture
+    fn get_lens_point(&self) -> Vec2 {
+        let radius = self.get_lens_radius();
+        match self.aperture {
+            Aperture::Pinhole => Vec2::ZERO,
+            Aperture::Disk => {
+                let angle = ptrandom::get_longitude();
+                Vec2::new(angle.cos(), angle.sin()) * ptrandom::get_unit().sqrt() * radius
+            },
+            Aperture::Polygon { blades, rotation } => {
+                let blade = (ptrandom::get_unit() * blades as f32).floor().min(blades as f32 - 1.0);
+                let step = std::f32::consts::PI * 2.0 / blades as f32;
+                let a1 = rotation + blade * step;
+                let a2 = a1 + step;
+                let (mut u, mut v) = (ptrandom::get_unit(), ptrandom::get_unit());
+                if u + v > 1.0 {
+                    u = 1.0 - u;
+                    v = 1.0 - v;
+                }
+                (Vec2::new(a1.cos(), a1.sin()) * u + Vec2::new(a2.cos(), a2.sin()) * v) * radius
+            }
+        }
+    }
+
+    fn get_screen_direction(&self, x: f32, y: f32, chroma_factor: f32) -> Vec3 {
+        let (width, height) = self.field_of_view.get_screen_size(self.aspect_ratio);
+        Vec3::new(x * width * chroma_factor, 1.0, -y * height * chroma_factor).normalize()
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn get_ray(&self, x: f32, y: f32, wavelength: f32) -> Ray {
+        let d = (wavelength - 580.0) / 200.0;
+        let chroma_zoom = 1.0 + d * self.chromatic_aberration;
+        let direction = self.get_screen_direction(x, y, chroma_zoom);
+        let focus_point = direction * (self.focal_distance / direction.y);
+        let lens = self.get_lens_point();
+        let lens_point = Vec3::new(lens.x, 0.0, lens.y);
+        Ray::new(
+            self.position + self.orientation.mul_vec3(lens_point),
+            self.orientation.mul_vec3(focus_point - lens_point).normalize(),
+            wavelength,
+            1.0
+        )
+    }
+
+    fn get_focus_ray(&self, x: f32, y: f32) -> Option<Ray> {
+        let direction = self.get_screen_direction(x, y, 1.0);
+        Some(Ray::new(self.position, self.orientation.mul_vec3(direction), 580.0, 1.0))
+    }
+
+    fn set_focus(&mut self, point: Vec3) {
+        self.focal_distance = self.orientation.inverse().mul_vec3(point - self.position).y;
+    }
+
+    fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.aspect_ratio = aspect_ratio;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+    use crate::camera::camera::{Camera, FieldOfView};
+    use crate::camera::perspective::{Aperture, PerspectiveCamera};
+
+    #[test]
+    fn lens_points_inside_polygon() {
+        let camera = PerspectiveCamera::new(Vec3::ZERO, Quat::IDENTITY, FieldOfView::Horizontal(1.0))
+            .with_lens(0.05, 2.0, 4.0)
+            .with_aperture(Aperture::Polygon { blades: 6, rotation: 0.3 });
+        let apothem = camera.get_lens_radius() * (std::f32::consts::PI / 6.0).cos();
+        for _ in 0..1000 {
+            let p = camera.get_lens_point();
+            let angle = p.y.atan2(p.x) - 0.3;
+            let sector = (angle / (std::f32::consts::PI / 3.0)).floor() * std::f32::consts::PI / 3.0 + std::f32::consts::PI / 6.0;
+            assert!(p.length() * (angle - sector).cos() <= apothem * 1.0001);
+        }
+    }
+
+    #[test]
+    fn rays_meet_in_focal_plane() {
+        let camera = PerspectiveCamera::new(Vec3::ZERO, Quat::IDENTITY, FieldOfView::Horizontal(1.0))
+            .with_lens(0.05, 1.4, 4.0);
+        let r1 = camera.get_ray(0.2, 0.1, 550.0);
+        let r2 = camera.get_ray(0.2, 0.1, 550.0);
+        let p1 = r1.position + r1.direction * ((4.0 - r1.position.y) / r1.direction.y);
+        let p2 = r2.position + r2.direction * ((4.0 - r2.position.y) / r2.direction.y);
+        assert!(p1.distance(p2) < 1.0e-4);
+    }
+
+    #[test]
+    fn focus_on_point() {
+        let mut camera = PerspectiveCamera::new(Vec3::new(1.0, 0.0, 0.0), Quat::from_rotation_z(0.5), FieldOfView::Horizontal(1.0));
+        let target = camera.get_focus_ray(0.3, -0.2).unwrap();
+        camera.set_focus(target.position + target.direction * 7.0);
+        let local = Quat::from_rotation_z(0.5).inverse().mul_vec3(target.direction);
+        assert!((camera.focal_distance - 7.0 * local.y).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn corners_match_field_of_view() {
+        let mut camera = PerspectiveCamera::look_at(Vec3::ZERO, Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0), FieldOfView::Vertical(std::f32::consts::FRAC_PI_2));
+        camera.set_aspect_ratio(2.0);
+        let top_right = camera.get_ray(1.0, -1.0, 550.0).direction;
+        assert!(top_right.distance(Vec3::new(2.0, 1.0, 1.0).normalize()) < 1.0e-5);
+    }
+}
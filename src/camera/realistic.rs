@@ -0,0 +1,351 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::path::Path;
+use glam::{Quat, Vec3};
+use crate::camera::camera::Camera;
+use crate::geometry::ray::Ray;
+use crate::geometry::util;
+use crate::material::dispersion::Dispersion;
+use crate::ptrandom;
+
+/// A single spherical surface of a lens prescription, lengths are given in mm
+#[derive(Clone, Debug)]
+pub struct LensElement {
+    /// Radius of curvature, positive if the center lies towards the film. Zero marks the aperture stop.
+    pub radius: f32,
+    /// Distance along the optical axis to the next surface, or to the film for the last one
+    pub thickness: f32,
+    /// Medium behind the surface, `None` for air
+    pub glass: Option<Dispersion>,
+    pub aperture: f32
+}
+
+/// Reads a lens prescription with one `radius thickness glass aperture` line per surface,
+/// ordered from the scene towards the film.
+pub fn read_lens(path: &Path) -> std::io::Result<Vec<LensElement>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut elements = vec![];
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, format!("line {}: {}", index + 1, message));
+        if columns.len() != 4 {
+            return Err(invalid("expected radius, thickness, glass and aperture"));
+        }
+        let number = |s: &str| s.parse::<f32>().map_err(|_| invalid(&format!("invalid number '{}'", s)));
+        let glass = match Dispersion::from_name(columns[2]) {
+            Some(Dispersion::Constant(1.0)) => None,
+            Some(glass) => Some(glass),
+            None => return Err(invalid(&format!("unknown glass '{}'", columns[2])))
+        };
+        elements.push(LensElement {
+            radius: number(columns[0])?,
+            thickness: number(columns[1])?,
+            glass,
+            aperture: number(columns[3])?
+        });
+    }
+    if elements.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, "lens has no elements"));
+    }
+    Ok(elements)
+}
+
+#[derive(Debug)]
+pub enum LensError {
+    NoElements,
+    /// A ray close to the optical axis does not make it through the lens, so it cannot be focused
+    BlockedParaxialRay
+}
+
+impl fmt::Display for LensError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LensError::NoElements => write!(f, "lens has no elements"),
+            LensError::BlockedParaxialRay => write!(f, "paraxial ray blocked by the lens system")
+        }
+    }
+}
+
+impl std::error::Error for LensError {}
+
+/// Traces camera rays through every element of a lens system. The lens coordinate system has the
+/// film at z = 0 and the optical axis pointing towards the scene along +z, lengths are in mm.
+pub struct RealisticCamera {
+    pub position: Vec3,
+    pub orientation: Quat,
+    elements: Vec<LensElement>,
+    /// Position of each surface on the optical axis
+    positions: Vec<f32>,
+    film_size: f32,
+    aspect_ratio: f32,
+    scale: f32,
+    /// Paraxial focal length, which focusing leaves unchanged
+    focal_length: f32,
+    /// Film and scene side principal planes relative to the rear surface, which move with it
+    principal_planes: (f32, f32)
+}
+
+impl RealisticCamera {
+
+    /// `film_size` is the width of the film in mm, `scale` the size of a mm in scene units.
+    /// The lens is moved so that objects at `focus_distance` (scene units) from the film are in focus.
+    pub fn new(position: Vec3,
+               orientation: Quat,
+               elements: Vec<LensElement>,
+               film_size: f32,
+               focus_distance: f32,
+               scale: f32) -> Result<RealisticCamera, LensError> {
+        if elements.is_empty() {
+            return Err(LensError::NoElements);
+        }
+        let mut camera = RealisticCamera {
+            position, orientation, elements, positions: vec![], film_size, aspect_ratio: 1.0, scale, focal_length: 0.0, principal_planes: (0.0, 0.0)
+        };
+        camera.update_positions();
+        camera.update_cardinal_points()?;
+        camera.focus(focus_distance / scale);
+        Ok(camera)
+    }
+
+    /// Moves the lens so that objects at `focus_distance` (mm, from the film) are in focus.
+    /// Distances closer than the lens can focus on are clamped to the closest one.
+    fn focus(&mut self, focus_distance: f32) {
+        let film_distance = self.get_film_distance(focus_distance);
+        let last = self.elements.len() - 1;
+        self.elements[last].thickness = film_distance;
+        self.update_positions();
+    }
+
+    fn update_positions(&mut self) {
+        let mut z = 0.0;
+        let mut positions = vec![0.0; self.elements.len()];
+        for (i, e) in self.elements.iter().enumerate().rev() {
+            z += e.thickness;
+            positions[i] = z;
+        }
+        self.positions = positions;
+    }
+
+    fn get_film_point(&self, x: f32, y: f32) -> Vec3 {
+        Vec3::new(-x * self.film_size * 0.5, -y * self.film_size * 0.5 / self.aspect_ratio, 0.0)
+    }
+
+    fn get_ior(&self, index: Option<usize>, wavelength: f32) -> f32 {
+        index.and_then(|i| self.elements[i].glass)
+            .map(|g| g.get_refraction_index(wavelength))
+            .unwrap_or(1.0)
+    }
+
+    /// Traces a ray through all surfaces, from the film towards the scene if the direction points
+    /// along +z and the other way round otherwise. Returns `None` if the ray is blocked.
+    fn trace(&self, mut position: Vec3, mut direction: Vec3, wavelength: f32) -> Option<(Vec3, Vec3)> {
+        let to_scene = direction.z > 0.0;
+        let order: Vec<usize> = if to_scene {
+            (0..self.elements.len()).rev().collect()
+        } else {
+            (0..self.elements.len()).collect()
+        };
+        for i in order {
+            let element = &self.elements[i];
+            let z = self.positions[i];
+            let aperture_squared = element.aperture * element.aperture * 0.25;
+            if element.radius == 0.0 {
+                let t = (z - position.z) / direction.z;
+                position += direction * t;
+                if position.x * position.x + position.y * position.y > aperture_squared {
+                    return None;
+                }
+                continue;
+            }
+
+            let center = Vec3::new(0.0, 0.0, z - element.radius);
+            let offset = position - center;
+            let b = offset.dot(direction);
+            let c = offset.length_squared() - element.radius * element.radius;
+            let discriminant = b * b - c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            let use_closer = to_scene == (element.radius < 0.0);
+            let t = if use_closer { -b - discriminant.sqrt() } else { -b + discriminant.sqrt() };
+            if t <= 0.0 {
+                return None;
+            }
+            position += direction * t;
+            if position.x * position.x + position.y * position.y > aperture_squared {
+                return None;
+            }
+
+            let mut normal = (position - center).normalize();
+            if normal.dot(direction) > 0.0 {
+                normal *= -1.0;
+            }
+            let front = if i == 0 { None } else { Some(i - 1) };
+            let (eta_i, eta_t) = if to_scene {
+                (self.get_ior(Some(i), wavelength), self.get_ior(front, wavelength))
+            } else {
+                (self.get_ior(front, wavelength), self.get_ior(Some(i), wavelength))
+            };
+            direction = util::refract(direction, normal, eta_i / eta_t)?.normalize();
+        }
+        Some((position, direction))
+    }
+
+    /// Finds the focal point and principal plane on the exit side of a paraxial ray entering
+    /// parallel to the axis
+    fn get_cardinal_points(&self, start: Vec3, direction: Vec3) -> Result<(f32, f32), LensError> {
+        let (position, direction) = self.trace(start, direction, 587.6).ok_or(LensError::BlockedParaxialRay)?;
+        let focal_point = position.z - position.x / direction.x * direction.z;
+        let principal_plane = position.z + (start.x - position.x) / direction.x * direction.z;
+        Ok((focal_point, principal_plane))
+    }
+
+    /// Traces paraxial rays through both sides of the lens for the thick lens approximation.
+    /// Focusing only moves the whole lens along the axis, so this is done once.
+    fn update_cardinal_points(&mut self) -> Result<(), LensError> {
+        let height = 0.001 * self.film_size;
+        let rear_surface = self.positions[self.elements.len() - 1];
+        let (film_focal_point, film_principal_plane) = self.get_cardinal_points(Vec3::new(height, 0.0, self.positions[0] + 1.0), Vec3::new(0.0, 0.0, -1.0))?;
+        let (_, scene_principal_plane) = self.get_cardinal_points(Vec3::new(height, 0.0, rear_surface - 1.0), Vec3::new(0.0, 0.0, 1.0))?;
+        self.focal_length = film_principal_plane - film_focal_point;
+        self.principal_planes = (film_principal_plane - rear_surface, scene_principal_plane - rear_surface);
+        Ok(())
+    }
+
+    /// Thick lens approximation of the distance between the rear element and the film that puts
+    /// objects at `focus_distance` (mm, from the film) into focus. Below four focal lengths
+    /// between the principal planes, the closest focus is used.
+    fn get_film_distance(&self, focus_distance: f32) -> f32 {
+        let (film_principal_plane, scene_principal_plane) = self.principal_planes;
+        // Solve the lens equation for u, the distance between the film and the rear principal plane
+        let d = (focus_distance - scene_principal_plane + film_principal_plane).max(4.0 * self.focal_length);
+        let u = 2.0 * self.focal_length / (1.0 + (1.0 - 4.0 * self.focal_length / d).sqrt());
+        u - film_principal_plane
+    }
+}
+
+impl Camera for RealisticCamera {
+    fn get_ray(&self, x: f32, y: f32, wavelength: f32) -> Ray {
+        // The lens projects an inverted image onto the film
+        let film_point = self.get_film_point(x, y);
+
+        let rear = self.elements.last().unwrap();
+        let angle = ptrandom::get_longitude();
+        let radius = ptrandom::get_unit().sqrt() * rear.aperture * 0.5;
+        let rear_point = Vec3::new(angle.cos() * radius, angle.sin() * radius, self.positions[self.elements.len() - 1]);
+        let direction = (rear_point - film_point).normalize();
+
+        let (strength, position, direction) = match self.trace(film_point, direction, wavelength) {
+            Some((position, out)) => (direction.z.powi(4), position, out),
+            None => (0.0, film_point, direction)
+        };
+        let position = Vec3::new(position.x, position.z, -position.y) * self.scale;
+        let direction = Vec3::new(direction.x, direction.z, -direction.y);
+        Ray::new(
+            self.position + self.orientation.mul_vec3(position),
+            self.orientation.mul_vec3(direction),
+            wavelength,
+            strength
+        )
+    }
+
+    fn get_focus_ray(&self, x: f32, y: f32) -> Option<Ray> {
+        let film_point = self.get_film_point(x, y);
+        let rear_point = Vec3::new(0.0, 0.0, self.positions[self.elements.len() - 1]);
+        let (position, direction) = self.trace(film_point, (rear_point - film_point).normalize(), 587.6)?;
+        Some(Ray::new(
+            self.position + self.orientation.mul_vec3(Vec3::new(position.x, position.z, -position.y) * self.scale),
+            self.orientation.mul_vec3(Vec3::new(direction.x, direction.z, -direction.y)),
+            587.6,
+            1.0
+        ))
+    }
+
+    fn set_focus(&mut self, point: Vec3) {
+        let distance = self.orientation.inverse().mul_vec3(point - self.position).y;
+        self.focus(distance / self.scale);
+    }
+
+    fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.aspect_ratio = aspect_ratio;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use glam::{Quat, Vec3};
+    use crate::camera::camera::Camera;
+    use crate::camera::realistic::{LensElement, LensError, RealisticCamera, read_lens};
+    use crate::material::dispersion::Dispersion;
+
+    fn biconvex_lens() -> Vec<LensElement> {
+        vec![
+            LensElement { radius: 50.0, thickness: 5.0, glass: Some(Dispersion::Constant(1.5)), aperture: 20.0 },
+            LensElement { radius: -50.0, thickness: 50.0, glass: None, aperture: 20.0 }
+        ]
+    }
+
+    #[test]
+    fn focuses_at_infinity() {
+        let camera = RealisticCamera::new(Vec3::ZERO, Quat::IDENTITY, biconvex_lens(), 10.0, f32::MAX, 1.0).unwrap();
+        // Lensmaker's equation for a thick lens gives f = 50.85 mm, with the principal planes 1.69 mm inside the lens
+        let film_distance = camera.elements[1].thickness;
+        assert!((film_distance - 49.15).abs() < 0.1, "film distance {}", film_distance);
+    }
+
+    #[test]
+    fn refocuses_and_clamps_close_focus() {
+        let mut camera = RealisticCamera::new(Vec3::ZERO, Quat::IDENTITY, biconvex_lens(), 10.0, 500.0, 1.0).unwrap();
+        camera.set_focus(Vec3::new(0.0, 1.0e9, 0.0));
+        assert!((camera.elements[1].thickness - 49.15).abs() < 0.1, "film distance {}", camera.elements[1].thickness);
+        // Points closer than four focal lengths are focused as well as the lens can
+        camera.set_focus(Vec3::new(0.0, 100.0, 0.0));
+        let closest = camera.elements[1].thickness;
+        camera.set_focus(Vec3::new(0.0, -5.0, 0.0));
+        assert_eq!(closest, camera.elements[1].thickness);
+        assert!(closest > 49.15 && closest.is_finite());
+    }
+
+    #[test]
+    fn reads_double_gauss() {
+        let elements = read_lens(Path::new("lenses/double_gauss_50mm.lens")).unwrap();
+        assert_eq!(11, elements.len());
+        let camera = RealisticCamera::new(Vec3::ZERO, Quat::IDENTITY, elements, 36.0, f32::MAX, 1.0).unwrap();
+        let film_distance = camera.elements[10].thickness;
+        assert!(film_distance > 30.0 && film_distance < 45.0, "film distance {}", film_distance);
+        let passed = (0..100).filter(|_| camera.get_ray(0.0, 0.0, 550.0).strength > 0.0).count();
+        assert!(passed > 10);
+    }
+
+    #[test]
+    fn center_ray_passes_along_axis() {
+        let camera = RealisticCamera::new(Vec3::ZERO, Quat::IDENTITY, biconvex_lens(), 10.0, 1000.0, 1.0).unwrap();
+        let (position, direction) = camera.trace(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), 550.0).unwrap();
+        assert!(position.x.abs() < 1.0e-5 && position.y.abs() < 1.0e-5);
+        assert!((direction.z - 1.0).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn rays_outside_the_aperture_are_blocked() {
+        let camera = RealisticCamera::new(Vec3::ZERO, Quat::IDENTITY, biconvex_lens(), 10.0, 1000.0, 1.0).unwrap();
+        assert!(camera.trace(Vec3::new(15.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), 550.0).is_none());
+        assert!(camera.trace(Vec3::new(5.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), 550.0).is_some());
+    }
+
+    #[test]
+    fn unusable_lenses_are_rejected() {
+        let empty = RealisticCamera::new(Vec3::ZERO, Quat::IDENTITY, vec![], 10.0, 1000.0, 1.0);
+        assert!(matches!(empty, Err(LensError::NoElements)));
+        let mut closed = biconvex_lens();
+        closed.insert(1, LensElement { radius: 0.0, thickness: 1.0, glass: Some(Dispersion::Constant(1.5)), aperture: 0.0 });
+        let blocked = RealisticCamera::new(Vec3::ZERO, Quat::IDENTITY, closed, 10.0, 1000.0, 1.0);
+        assert!(matches!(blocked, Err(LensError::BlockedParaxialRay)));
+    }
+}
@@ -1,35 +1,48 @@
 use bvh::aabb::{AABB, Bounded};
 use bvh::bounding_hierarchy::BHShape;
-use crate::entity::Entity::{DARK, LUMINOUS};
 use crate::geometry::surface::Surface;
 use crate::material::material::{Material, Radiator};
 
-pub enum Entity {
-    DARK(Box<dyn Surface>, Box<dyn Material>),
-    LUMINOUS(Box<dyn Surface>, Box<dyn Radiator>)
+/// A surface that scatters light with its material, emits light with its radiator, or both.
+/// Surfaces without a material absorb every ray that hits them.
+pub struct Entity {
+    pub surface: Box<dyn Surface>,
+    pub material: Option<Box<dyn Material>>,
+    pub radiator: Option<Box<dyn Radiator>>
+}
+
+impl Entity {
+    pub fn dark(surface: Box<dyn Surface>, material: Box<dyn Material>) -> Entity {
+        Entity { surface, material: Some(material), radiator: None }
+    }
+
+    pub fn luminous(surface: Box<dyn Surface>, radiator: Box<dyn Radiator>) -> Entity {
+        Entity { surface, material: None, radiator: Some(radiator) }
+    }
+
+    pub fn with_material(mut self, material: Box<dyn Material>) -> Entity {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn with_radiator(mut self, radiator: Box<dyn Radiator>) -> Entity {
+        self.radiator = Some(radiator);
+        self
+    }
 }
 
 impl Bounded for Entity {
     fn aabb(&self) -> AABB {
-        match self {
-            DARK(s, _) => s.aabb(),
-            LUMINOUS(s, _) => s.aabb()
-        }
+        self.surface.aabb()
     }
 }
 
 impl BHShape for Entity {
     fn set_bh_node_index(&mut self, index: usize) {
-        match self {
-            DARK(s, _) => s.set_bh_node_index(index),
-            LUMINOUS(s, _) => s.set_bh_node_index(index)
-        }
+        self.surface.set_bh_node_index(index)
     }
 
     fn bh_node_index(&self) -> usize {
-        match self {
-            DARK(s, _) => s.bh_node_index(),
-            LUMINOUS(s, _) => s.bh_node_index()
-        }
+        self.surface.bh_node_index()
     }
 }
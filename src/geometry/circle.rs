@@ -1,6 +1,7 @@
 use bvh::aabb::{AABB, Bounded};
 use bvh::bounding_hierarchy::BHShape;
-use glam::Vec3;
+use glam::{Vec2, Vec3};
+use crate::geometry::util;
 use crate::geometry::intersection::Intersection;
 use crate::geometry::ray::Ray;
 use crate::geometry::surface::Surface;
@@ -42,18 +43,18 @@ impl Surface for Circle {
             return None;
         }
         let t = -self.normal.dot(origin) / d;
-        if t <= 0.0 {
+        if !ray.contains(t) {
             return None;
         }
         let pos = ray.position + ray.direction * t;
         if pos.distance_squared(self.position) > self.radius_squared {
             return None;
         }
-        let normal = if d >= 0.0 {
-            self.normal * -1.0
-        } else {
-            self.normal
-        };
-        Some(Intersection::new(pos, normal, Vec3::new(0.0, 0.0, 0.0), t * t))
+        // Planar mapping of the disc onto the unit square
+        let tangent = util::get_tangent(self.normal);
+        let bitangent = self.normal.cross(tangent);
+        let offset = (pos - self.position) / (2.0 * self.radius_squared.sqrt());
+        let uv = Vec2::new(0.5 + offset.dot(tangent), 0.5 + offset.dot(bitangent));
+        Some(Intersection::new(ray, t, self.normal, tangent).with_uv(uv))
     }
 }
\ No newline at end of file
@@ -0,0 +1,103 @@
+use bvh::aabb::{AABB, Bounded};
+use bvh::bounding_hierarchy::BHShape;
+use glam::{Quat, Vec3};
+use crate::geometry::cylinder::{get_disc_bounds, get_side_uv, intersect_cap, to_intersections, LocalHit};
+use crate::geometry::intersection::Intersection;
+use crate::geometry::polynomial;
+use crate::geometry::ray::Ray;
+use crate::geometry::surface::Surface;
+use crate::geometry::util;
+
+/// Cone with its base disc at `position`, closed with a flat cap, and its tip `height` along `axis`
+pub struct Cone {
+    position: Vec3,
+    axis: Vec3,
+    radius: f32,
+    height: f32,
+    rotation: Quat,
+    node_index: usize
+}
+
+impl Cone {
+    pub fn new(position: Vec3, axis: Vec3, radius: f32, height: f32) -> Cone {
+        let axis = axis.normalize();
+        Cone { position, axis, radius, height, rotation: Quat::from_rotation_arc(Vec3::Z, axis), node_index: 0 }
+    }
+}
+
+impl Bounded for Cone {
+    fn aabb(&self) -> AABB {
+        let [a, b] = get_disc_bounds(self.position, self.axis, self.radius);
+        util::get_aabb(&[a, b, self.position + self.axis * self.height])
+    }
+}
+
+impl BHShape for Cone {
+    fn set_bh_node_index(&mut self, index: usize) {
+        self.node_index = index;
+    }
+
+    fn bh_node_index(&self) -> usize {
+        self.node_index
+    }
+}
+
+impl Surface for Cone {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.intersect_all(ray).into_iter().next()
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let inverse = self.rotation.inverse();
+        let o = inverse.mul_vec3(ray.position - self.position);
+        let d = inverse.mul_vec3(ray.direction);
+        // x² + y² = k² (h - z)²
+        let k2 = (self.radius / self.height).powi(2);
+        let h = self.height - o.z;
+        let a = d.x * d.x + d.y * d.y - k2 * d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.y * d.y + k2 * h * d.z);
+        let c = o.x * o.x + o.y * o.y - k2 * h * h;
+        let mut hits: Vec<LocalHit> = polynomial::solve_quadratic(a as f64, b as f64, c as f64)
+            .into_iter()
+            .map(|t| (t as f32, o + d * t as f32))
+            .filter(|(_, p)| p.z >= 0.0 && p.z <= self.height)
+            .map(|(t, p)| (t, Vec3::new(p.x, p.y, k2 * (self.height - p.z)), get_side_uv(p, self.height)))
+            .collect();
+        hits.extend(intersect_cap(o, d, 0.0, self.radius, Vec3::new(0.0, 0.0, -1.0)));
+        to_intersections(ray, self.rotation, hits, self.axis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::geometry::cone::Cone;
+    use crate::geometry::ray::Ray;
+    use crate::geometry::surface::Surface;
+
+    #[test]
+    fn slanted_side() {
+        let cone = Cone::new(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), 1.0, 1.0);
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.5), Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        let hits = cone.intersect_all(&ray);
+        assert_eq!(2, hits.len());
+        assert!((hits[0].position.x + 0.5).abs() < 1.0e-5);
+        let expected = Vec3::new(-1.0, 0.0, 1.0).normalize();
+        assert!((hits[0].normal - expected).length() < 1.0e-5);
+    }
+
+    #[test]
+    fn base_and_mirror_cone_are_ignored() {
+        let cone = Cone::new(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), 1.0, 1.0);
+        let up = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 550.0, 1.0);
+        let hits = cone.intersect_all(&up);
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), hits[0].normal);
+        assert!((hits[1].position.z - 1.0).abs() < 1.0e-5);
+        let base = cone.intersect(&Ray::new(Vec3::new(0.2, 0.6, -5.0), Vec3::new(0.0, 0.0, 1.0), 550.0, 1.0)).unwrap();
+        // v grows along the bitangent of the base, which faces -z
+        assert!((base.get_bitangent() - Vec3::new(0.0, -1.0, 0.0)).length() < 1.0e-5);
+        assert!((base.uv - glam::Vec2::new(0.6, 0.2)).length() < 1.0e-5, "{:?}", base.uv);
+        let above = Ray::new(Vec3::new(-5.0, 0.0, 1.5), Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        assert!(cone.intersect(&above).is_none());
+    }
+}
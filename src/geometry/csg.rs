@@ -0,0 +1,153 @@
+use bvh::aabb::{AABB, Bounded};
+use bvh::bounding_hierarchy::BHShape;
+use crate::geometry::intersection::Intersection;
+use crate::geometry::ray::Ray;
+use crate::geometry::surface::Surface;
+
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference
+}
+
+impl CsgOperation {
+    fn is_inside(&self, inside_a: bool, inside_b: bool) -> bool {
+        match self {
+            CsgOperation::Union => inside_a || inside_b,
+            CsgOperation::Intersection => inside_a && inside_b,
+            CsgOperation::Difference => inside_a && !inside_b
+        }
+    }
+}
+
+/// Boolean combination of two closed surfaces. Whether the ray starts inside a child follows from
+/// the number of times it crosses it, so the children have to be watertight.
+pub struct Csg {
+    operation: CsgOperation,
+    a: Box<dyn Surface>,
+    b: Box<dyn Surface>,
+    node_index: usize
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, a: Box<dyn Surface>, b: Box<dyn Surface>) -> Csg {
+        Csg { operation, a, b, node_index: 0 }
+    }
+
+    pub fn union(a: Box<dyn Surface>, b: Box<dyn Surface>) -> Csg {
+        Csg::new(CsgOperation::Union, a, b)
+    }
+
+    pub fn intersection(a: Box<dyn Surface>, b: Box<dyn Surface>) -> Csg {
+        Csg::new(CsgOperation::Intersection, a, b)
+    }
+
+    /// Removes `b` from `a`
+    pub fn difference(a: Box<dyn Surface>, b: Box<dyn Surface>) -> Csg {
+        Csg::new(CsgOperation::Difference, a, b)
+    }
+}
+
+impl Bounded for Csg {
+    fn aabb(&self) -> AABB {
+        let a = self.a.aabb();
+        let b = self.b.aabb();
+        match self.operation {
+            CsgOperation::Union => a.join(&b),
+            CsgOperation::Intersection => AABB::with_bounds(a.min.max(b.min), a.max.min(b.max)),
+            CsgOperation::Difference => a
+        }
+    }
+}
+
+impl BHShape for Csg {
+    fn set_bh_node_index(&mut self, index: usize) {
+        self.node_index = index;
+    }
+
+    fn bh_node_index(&self) -> usize {
+        self.node_index
+    }
+}
+
+impl Surface for Csg {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.intersect_all(ray).into_iter().next()
+    }
+
+    /// Walks along the crossings of both children and keeps those where the ray enters or leaves
+    /// the combined solid. Normals point out of the combined solid.
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        // Crossings beyond the end of the interval still count towards starting inside
+        let unbounded = ray.with_interval(ray.t_min, f32::INFINITY);
+        let hits_a = self.a.intersect_all(&unbounded);
+        let hits_b = self.b.intersect_all(&unbounded);
+        let mut inside_a = hits_a.len() % 2 == 1;
+        let mut inside_b = hits_b.len() % 2 == 1;
+        let mut events: Vec<(bool, Intersection)> = hits_a.into_iter().map(|i| (true, i))
+            .chain(hits_b.into_iter().map(|i| (false, i)))
+            .collect();
+        events.sort_by(|a, b| a.1.t.total_cmp(&b.1.t));
+
+        let mut result = vec![];
+        for (from_a, i) in events {
+            let before = self.operation.is_inside(inside_a, inside_b);
+            if from_a {
+                inside_a = !inside_a;
+            } else {
+                inside_b = !inside_b;
+            }
+            let after = self.operation.is_inside(inside_a, inside_b);
+            if before != after && i.t < ray.t_max {
+                result.push(if i.front_face == after { i } else { i.flip() });
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::geometry::csg::Csg;
+    use crate::geometry::ray::Ray;
+    use crate::geometry::sphere::Sphere;
+    use crate::geometry::surface::Surface;
+
+    fn spheres() -> (Box<Sphere>, Box<Sphere>) {
+        (Box::new(Sphere::new(Vec3::new(-0.5, 0.0, 0.0), 1.0)), Box::new(Sphere::new(Vec3::new(0.5, 0.0, 0.0), 1.0)))
+    }
+
+    fn crossings(surface: &dyn Surface, ray: &Ray) -> Vec<f32> {
+        surface.intersect_all(ray).iter().map(|i| i.position.x).collect()
+    }
+
+    #[test]
+    fn union_intersection_difference() {
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        let (a, b) = spheres();
+        assert_eq!(vec![-1.5, 1.5], crossings(&Csg::union(a, b), &ray));
+        let (a, b) = spheres();
+        assert_eq!(vec![-0.5, 0.5], crossings(&Csg::intersection(a, b), &ray));
+        let (a, b) = spheres();
+        assert_eq!(vec![-1.5, -0.5], crossings(&Csg::difference(a, b), &ray));
+    }
+
+    #[test]
+    fn difference_normals_point_outwards() {
+        let (a, b) = spheres();
+        let lens = Csg::difference(a, b);
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        let hits = lens.intersect_all(&ray);
+        assert_eq!(Vec3::new(-1.0, 0.0, 0.0), hits[0].normal);
+        assert_eq!(Vec3::new(1.0, 0.0, 0.0), hits[1].normal);
+    }
+
+    #[test]
+    fn ray_starting_inside() {
+        let (a, b) = spheres();
+        let union = Csg::union(a, b);
+        let ray = Ray::new(Vec3::new(-0.5, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        assert_eq!(vec![1.5], crossings(&union, &ray));
+    }
+}
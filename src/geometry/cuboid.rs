@@ -0,0 +1,144 @@
+use bvh::aabb::{AABB, Bounded};
+use bvh::bounding_hierarchy::BHShape;
+use glam::{Quat, Vec2, Vec3};
+use crate::geometry::intersection::Intersection;
+use crate::geometry::ray::Ray;
+use crate::geometry::surface::Surface;
+use crate::geometry::util;
+
+/// Box around `position`, extending `half_size` along each of its rotated axes
+pub struct Cuboid {
+    position: Vec3,
+    half_size: Vec3,
+    rotation: Quat,
+    node_index: usize
+}
+
+impl Cuboid {
+    pub fn new(position: Vec3, half_size: Vec3, rotation: Quat) -> Cuboid {
+        Cuboid { position, half_size, rotation, node_index: 0 }
+    }
+
+    /// Axis aligned box between two corners
+    pub fn from_corners(min: Vec3, max: Vec3) -> Cuboid {
+        Cuboid::new((min + max) * 0.5, (max - min).abs() * 0.5, Quat::IDENTITY)
+    }
+
+    /// Entry and exit distance along the ray with the local axis and sign of the face normals
+    fn get_distances(&self, ray: &Ray) -> Option<[(f32, usize, f32); 2]> {
+        let inverse = self.rotation.inverse();
+        let origin = inverse.mul_vec3(ray.position - self.position);
+        let direction = inverse.mul_vec3(ray.direction);
+        let mut entry = (f32::NEG_INFINITY, 0, 0.0);
+        let mut exit = (f32::INFINITY, 0, 0.0);
+        for axis in 0..3 {
+            let t1 = (-self.half_size[axis] - origin[axis]) / direction[axis];
+            let t2 = (self.half_size[axis] - origin[axis]) / direction[axis];
+            let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+            if near > entry.0 {
+                entry = (near, axis, -direction[axis].signum());
+            }
+            if far < exit.0 {
+                exit = (far, axis, direction[axis].signum());
+            }
+        }
+        if entry.0 > exit.0 || exit.0 <= 0.0 {
+            return None;
+        }
+        Some([entry, exit])
+    }
+
+    fn get_intersection(&self, ray: &Ray, (t, axis, sign): (f32, usize, f32)) -> Intersection {
+        let mut normal = Vec3::ZERO;
+        normal[axis] = sign;
+        let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut tangent = Vec3::ZERO;
+        tangent[b] = 1.0;
+        // Each face maps onto the unit square, with v following the bitangent normal × tangent
+        let position = ray.position + ray.direction * t;
+        let local = self.rotation.inverse().mul_vec3(position - self.position) / self.half_size;
+        let uv = Vec2::new(0.5 + 0.5 * local[b], 0.5 + 0.5 * sign * local[c]);
+        Intersection::new(ray, t, self.rotation.mul_vec3(normal), self.rotation.mul_vec3(tangent)).with_uv(uv)
+    }
+}
+
+impl Bounded for Cuboid {
+    fn aabb(&self) -> AABB {
+        let corners: Vec<Vec3> = (0..8)
+            .map(|i| Vec3::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 }))
+            .map(|c| self.position + self.rotation.mul_vec3(c * self.half_size))
+            .collect();
+        util::get_aabb(&corners)
+    }
+}
+
+impl BHShape for Cuboid {
+    fn set_bh_node_index(&mut self, index: usize) {
+        self.node_index = index;
+    }
+
+    fn bh_node_index(&self) -> usize {
+        self.node_index
+    }
+}
+
+impl Surface for Cuboid {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.intersect_all(ray).into_iter().next()
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        match self.get_distances(ray) {
+            None => vec![],
+            Some(hits) => hits.iter()
+                .filter(|h| ray.contains(h.0))
+                .map(|h| self.get_intersection(ray, *h))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+    use bvh::aabb::Bounded;
+    use glam::{Quat, Vec3};
+    use crate::geometry::cuboid::Cuboid;
+    use crate::geometry::ray::Ray;
+    use crate::geometry::surface::Surface;
+
+    #[test]
+    fn axis_aligned_entry_and_exit() {
+        let cuboid = Cuboid::from_corners(Vec3::new(-1.0, -2.0, -3.0), Vec3::new(1.0, 2.0, 3.0));
+        let ray = Ray::new(Vec3::new(0.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 550.0, 1.0);
+        let hits = cuboid.intersect_all(&ray);
+        assert_eq!(2, hits.len());
+        assert_eq!(Vec3::new(0.0, -2.0, 0.0), hits[0].position);
+        assert_eq!(Vec3::new(0.0, -1.0, 0.0), hits[0].normal);
+        assert_eq!(Vec3::new(0.0, 1.0, 0.0), hits[1].normal);
+        assert_eq!(glam::Vec2::new(0.5, 0.5), hits[0].uv);
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), hits[0].tangent);
+    }
+
+    #[test]
+    fn rotated_box() {
+        let cuboid = Cuboid::new(Vec3::ZERO, Vec3::splat(1.0), Quat::from_rotation_z(PI * 0.25));
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        let hit = cuboid.intersect(&ray).unwrap();
+        assert!((hit.position.x + 2.0_f32.sqrt()).abs() < 1.0e-5);
+        let aabb = cuboid.aabb();
+        assert!((aabb.max.x - 2.0_f32.sqrt()).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn miss_and_inside() {
+        let cuboid = Cuboid::from_corners(Vec3::splat(-1.0), Vec3::splat(1.0));
+        let miss = Ray::new(Vec3::new(-5.0, 1.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        assert!(cuboid.intersect(&miss).is_none());
+        let inside = Ray::new(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), 550.0, 1.0);
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), cuboid.intersect(&inside).unwrap().normal);
+    }
+}
@@ -0,0 +1,154 @@
+use bvh::aabb::{AABB, Bounded};
+use bvh::bounding_hierarchy::BHShape;
+use std::f32::consts::PI;
+use glam::{Quat, Vec2, Vec3};
+use crate::geometry::intersection::Intersection;
+use crate::geometry::polynomial;
+use crate::geometry::ray::Ray;
+use crate::geometry::surface::Surface;
+use crate::geometry::util;
+
+/// Distances along a ray in the local frame of a shape together with the local normal and the
+/// texture coordinates
+pub(crate) type LocalHit = (f32, Vec3, Vec2);
+
+/// Intersections of the ray with the cap at `z` of radius `radius` in a local frame. Caps facing
+/// -z are mirrored in v, so that v increases along the bitangent of the local x tangent.
+pub(crate) fn intersect_cap(origin: Vec3, direction: Vec3, z: f32, radius: f32, normal: Vec3) -> Option<LocalHit> {
+    if direction.z == 0.0 {
+        return None;
+    }
+    let t = (z - origin.z) / direction.z;
+    let p = origin + direction * t;
+    if p.x * p.x + p.y * p.y > radius * radius {
+        return None;
+    }
+    let v = 0.5 * normal.z.signum() * p.y / radius;
+    Some((t, normal, Vec2::new(0.5 + 0.5 * p.x / radius, 0.5 + v)))
+}
+
+/// Cylindrical mapping of a local point on the side of a shape around the z axis
+pub(crate) fn get_side_uv(p: Vec3, height: f32) -> Vec2 {
+    Vec2::new((p.y.atan2(p.x) + PI) / (2.0 * PI), p.z / height)
+}
+
+/// Turns local hits into intersections sorted by distance, dropping those outside of the ray interval
+pub(crate) fn to_intersections(ray: &Ray, rotation: Quat, mut hits: Vec<LocalHit>, axis: Vec3) -> Vec<Intersection> {
+    hits.retain(|h| ray.contains(h.0));
+    hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+    hits.into_iter()
+        .map(|(t, normal, uv)| {
+            let normal = rotation.mul_vec3(normal).normalize();
+            let around = axis.cross(normal);
+            let tangent = if around.length_squared() > 1.0e-6 { around.normalize() } else { rotation.mul_vec3(Vec3::X) };
+            Intersection::new(ray, t, normal, tangent).with_uv(uv)
+        })
+        .collect()
+}
+
+/// Extent of a disc of `radius` perpendicular to `axis` around `center`
+pub(crate) fn get_disc_bounds(center: Vec3, axis: Vec3, radius: f32) -> [Vec3; 2] {
+    let extent = (Vec3::ONE - axis * axis).max(Vec3::ZERO);
+    let extent = Vec3::new(extent.x.sqrt(), extent.y.sqrt(), extent.z.sqrt()) * radius;
+    [center - extent, center + extent]
+}
+
+/// Cylinder closed with flat caps, starting at `position` and reaching `height` along `axis`
+pub struct Cylinder {
+    position: Vec3,
+    axis: Vec3,
+    radius: f32,
+    height: f32,
+    rotation: Quat,
+    node_index: usize
+}
+
+impl Cylinder {
+    pub fn new(position: Vec3, axis: Vec3, radius: f32, height: f32) -> Cylinder {
+        let axis = axis.normalize();
+        Cylinder { position, axis, radius, height, rotation: Quat::from_rotation_arc(Vec3::Z, axis), node_index: 0 }
+    }
+}
+
+impl Bounded for Cylinder {
+    fn aabb(&self) -> AABB {
+        let [a, b] = get_disc_bounds(self.position, self.axis, self.radius);
+        let [c, d] = get_disc_bounds(self.position + self.axis * self.height, self.axis, self.radius);
+        util::get_aabb(&[a, b, c, d])
+    }
+}
+
+impl BHShape for Cylinder {
+    fn set_bh_node_index(&mut self, index: usize) {
+        self.node_index = index;
+    }
+
+    fn bh_node_index(&self) -> usize {
+        self.node_index
+    }
+}
+
+impl Surface for Cylinder {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.intersect_all(ray).into_iter().next()
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let inverse = self.rotation.inverse();
+        let o = inverse.mul_vec3(ray.position - self.position);
+        let d = inverse.mul_vec3(ray.direction);
+        let a = d.x * d.x + d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.y * d.y);
+        let c = o.x * o.x + o.y * o.y - self.radius * self.radius;
+        let mut hits: Vec<LocalHit> = if a > 0.0 { polynomial::solve_quadratic(a as f64, b as f64, c as f64) } else { vec![] }
+            .into_iter()
+            .map(|t| (t as f32, o + d * t as f32))
+            .filter(|(_, p)| p.z >= 0.0 && p.z <= self.height)
+            .map(|(t, p)| (t, Vec3::new(p.x, p.y, 0.0), get_side_uv(p, self.height)))
+            .collect();
+        hits.extend(intersect_cap(o, d, 0.0, self.radius, Vec3::new(0.0, 0.0, -1.0)));
+        hits.extend(intersect_cap(o, d, self.height, self.radius, Vec3::new(0.0, 0.0, 1.0)));
+        to_intersections(ray, self.rotation, hits, self.axis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::geometry::cylinder::Cylinder;
+    use crate::geometry::ray::Ray;
+    use crate::geometry::surface::Surface;
+
+    #[test]
+    fn side_and_caps() {
+        let cylinder = Cylinder::new(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), 1.0, 2.0);
+        let side = Ray::new(Vec3::new(-5.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        let hits = cylinder.intersect_all(&side);
+        assert_eq!(2, hits.len());
+        assert!((hits[0].position - Vec3::new(-1.0, 0.0, 1.0)).length() < 1.0e-5);
+        assert!((hits[0].normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1.0e-5);
+        assert!((hits[0].uv - glam::Vec2::new(1.0, 0.5)).length() < 1.0e-5);
+        assert!((hits[0].tangent - Vec3::new(0.0, -1.0, 0.0)).length() < 1.0e-5);
+
+        let down = Ray::new(Vec3::new(0.5, 0.25, 5.0), Vec3::new(0.0, 0.0, -1.0), 550.0, 1.0);
+        let hits = cylinder.intersect_all(&down);
+        assert_eq!(2, hits.len());
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), hits[0].normal);
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), hits[1].normal);
+        // u and v grow along the tangent and bitangent from the center of each cap
+        for (hit, center) in hits.iter().zip([Vec3::new(0.0, 0.0, 2.0), Vec3::ZERO]) {
+            let offset = hit.position - center;
+            let expected = glam::Vec2::new(0.5 + 0.5 * offset.dot(hit.tangent), 0.5 + 0.5 * offset.dot(hit.get_bitangent()));
+            assert!((hit.uv - expected).length() < 1.0e-5, "{:?} {:?}", hit.uv, expected);
+        }
+    }
+
+    #[test]
+    fn oriented_cylinder_misses_past_its_end() {
+        let cylinder = Cylinder::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), 1.0, 2.0);
+        let past = Ray::new(Vec3::new(3.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 550.0, 1.0);
+        assert!(cylinder.intersect(&past).is_none());
+        let through = Ray::new(Vec3::new(1.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 550.0, 1.0);
+        assert!((cylinder.intersect(&through).unwrap().position.y + 1.0).abs() < 1.0e-5);
+    }
+}
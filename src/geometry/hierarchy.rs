@@ -0,0 +1,404 @@
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+use std::fmt;
+use std::time::{Duration, Instant};
+use glam::{Vec3, Vec3A};
+use crate::geometry::ray::Ray;
+
+const BIN_COUNT: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+/// Cost of visiting a node relative to testing a primitive
+const TRAVERSAL_COST: f32 = 1.0;
+const STACK_SIZE: usize = 64;
+/// Widens the exit distance of box tests by the worst rounding error of the slab computation,
+/// 2γ(3) after Ize, "Robust BVH Ray Traversal", so that boxes grazed by a ray are never missed
+const EXIT_SCALE: f32 = 1.0 + 3.0 * f32::EPSILON / (1.0 - 1.5 * f32::EPSILON);
+
+#[derive(Clone, Copy, Debug)]
+struct Bounds {
+    min: Vec3A,
+    max: Vec3A
+}
+
+impl Bounds {
+    const EMPTY: Bounds = Bounds { min: glam::const_vec3a!([f32::MAX; 3]), max: glam::const_vec3a!([f32::MIN; 3]) };
+
+    fn union(&self, other: &Bounds) -> Bounds {
+        Bounds { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    fn grow(&self, point: Vec3A) -> Bounds {
+        Bounds { min: self.min.min(point), max: self.max.max(point) }
+    }
+
+    fn get_surface_area(&self) -> f32 {
+        let size = (self.max - self.min).max(Vec3A::ZERO);
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    /// Distance along the ray where it enters the box, if it does so before `t_max`, with a
+    /// conservatively rounded exit
+    fn intersect(&self, origin: Vec3A, inverse_direction: Vec3A, t_max: f32) -> Option<f32> {
+        let t1 = (self.min - origin) * inverse_direction;
+        let t2 = (self.max - origin) * inverse_direction;
+        let near = t1.min(t2).max_element().max(0.0);
+        let far = (t1.max(t2).min_element() * EXIT_SCALE).min(t_max);
+        if near <= far { Some(near) } else { None }
+    }
+
+    /// Same as `intersect` for two boxes, e.g. both children of a node
+    fn intersect_pair(boxes: [&Bounds; 2], origin: Vec3A, inverse_direction: Vec3A, t_max: f32) -> [Option<f32>; 2] {
+        #[cfg(target_arch = "x86_64")]
+        unsafe { Bounds::intersect_pair_sse(boxes, origin, inverse_direction, t_max) }
+        #[cfg(not(target_arch = "x86_64"))]
+        boxes.map(|b| b.intersect(origin, inverse_direction, t_max))
+    }
+
+    /// Tests both boxes at once, with one register per axis that holds the minimum of both
+    /// boxes followed by their maximum
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn intersect_pair_sse([a, b]: [&Bounds; 2], origin: Vec3A, inverse_direction: Vec3A, t_max: f32) -> [Option<f32>; 2] {
+        let (min_xy, min_z) = (_mm_unpacklo_ps(a.min.into(), b.min.into()), _mm_unpackhi_ps(a.min.into(), b.min.into()));
+        let (max_xy, max_z) = (_mm_unpacklo_ps(a.max.into(), b.max.into()), _mm_unpackhi_ps(a.max.into(), b.max.into()));
+        let slabs = [_mm_movelh_ps(min_xy, max_xy), _mm_movehl_ps(max_xy, min_xy), _mm_movelh_ps(min_z, max_z)];
+        let mut near = _mm_setzero_ps();
+        let mut far = _mm_set1_ps(f32::INFINITY);
+        for (axis, slab) in slabs.iter().enumerate() {
+            let t = _mm_mul_ps(_mm_sub_ps(*slab, _mm_set1_ps(origin[axis])), _mm_set1_ps(inverse_direction[axis]));
+            // Distances to the other side of each slab, so that both halves hold near and far
+            let swapped = _mm_shuffle_ps::<0b01_00_11_10>(t, t);
+            near = _mm_max_ps(near, _mm_min_ps(t, swapped));
+            far = _mm_min_ps(far, _mm_max_ps(t, swapped));
+        }
+        far = _mm_min_ps(_mm_mul_ps(far, _mm_set1_ps(EXIT_SCALE)), _mm_set1_ps(t_max));
+        let hits = _mm_movemask_ps(_mm_cmple_ps(near, far));
+        let mut distances = [0.0_f32; 4];
+        _mm_storeu_ps(distances.as_mut_ptr(), near);
+        [0, 1].map(|i| if hits & (1 << i) != 0 { Some(distances[i]) } else { None })
+    }
+}
+
+/// Node of the flattened hierarchy. Leaves hold `count` primitives from `offset` on, inner nodes
+/// are followed by their first child and keep the index of the second one in `offset`.
+struct Node {
+    bounds: Bounds,
+    offset: u32,
+    count: u32,
+    /// Number the user of the hierarchy assigned to a leaf
+    tag: u32,
+    axis: u8
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BvhStats {
+    pub primitives: usize,
+    pub nodes: usize,
+    pub leaves: usize,
+    pub max_depth: usize,
+    pub max_leaf_size: usize,
+    /// Expected cost of a ray through the root by the surface area heuristic, the highest one of
+    /// merged hierarchies
+    pub sah_cost: f32,
+    pub build_time: Duration
+}
+
+impl BvhStats {
+    /// Combines the statistics of several hierarchies, e.g. of all meshes in a scene. Counts and
+    /// build times add up, depth, leaf size and SAH cost are those of the worst hierarchy.
+    pub fn merge(&mut self, other: &BvhStats) {
+        self.primitives += other.primitives;
+        self.nodes += other.nodes;
+        self.leaves += other.leaves;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.max_leaf_size = self.max_leaf_size.max(other.max_leaf_size);
+        self.sah_cost = self.sah_cost.max(other.sah_cost);
+        self.build_time += other.build_time;
+    }
+}
+
+impl fmt::Display for BvhStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} primitives in {} nodes ({} leaves, depth up to {}, at most {} per leaf, SAH cost up to {:.1}) built in {:.3}s",
+               self.primitives, self.nodes, self.leaves, self.max_depth, self.max_leaf_size, self.sah_cost, self.build_time.as_secs_f32())
+    }
+}
+
+struct Item {
+    index: usize,
+    bounds: Bounds,
+    centroid: Vec3A
+}
+
+/// Bounding volume hierarchy built with the binned surface area heuristic
+pub struct Bvh {
+    nodes: Vec<Node>,
+    stats: BvhStats
+}
+
+impl Bvh {
+    /// Builds the hierarchy over primitives given by their bounds. Leaves refer to ranges of
+    /// primitives, so they have to be stored in the returned order.
+    pub fn build(bounds: &[(Vec3, Vec3)]) -> (Bvh, Vec<usize>) {
+        let start = Instant::now();
+        let mut items: Vec<Item> = bounds.iter()
+            .enumerate()
+            .map(|(index, (min, max))| {
+                let bounds = Bounds { min: Vec3A::from(*min), max: Vec3A::from(*max) };
+                Item { index, bounds, centroid: (bounds.min + bounds.max) * 0.5 }
+            })
+            .collect();
+        let mut bvh = Bvh { nodes: Vec::with_capacity(2 * bounds.len()), stats: BvhStats { primitives: bounds.len(), ..BvhStats::default() } };
+        bvh.build_node(&mut items, 0, 1);
+        let root_area = bvh.nodes[0].bounds.get_surface_area().max(f32::MIN_POSITIVE);
+        bvh.stats.nodes = bvh.nodes.len();
+        bvh.stats.sah_cost = bvh.nodes.iter()
+            .map(|n| n.bounds.get_surface_area() / root_area * if n.count > 0 { n.count as f32 } else { TRAVERSAL_COST })
+            .sum();
+        bvh.stats.build_time = start.elapsed();
+        (bvh, items.into_iter().map(|i| i.index).collect())
+    }
+
+    pub fn get_stats(&self) -> &BvhStats {
+        &self.stats
+    }
+
+    pub fn get_bounds(&self) -> (Vec3, Vec3) {
+        (self.nodes[0].bounds.min.into(), self.nodes[0].bounds.max.into())
+    }
+
+    fn build_node(&mut self, items: &mut [Item], offset: usize, depth: usize) -> usize {
+        let bounds = items.iter().fold(Bounds::EMPTY, |b, i| b.union(&i.bounds));
+        let index = self.nodes.len();
+        self.nodes.push(Node { bounds, offset: offset as u32, count: items.len() as u32, tag: 0, axis: 0 });
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+
+        let split = if depth < STACK_SIZE - 1 { Bvh::find_split(items, &bounds) } else { None };
+        let (axis, middle) = match split {
+            Some(split) => split,
+            None => {
+                self.stats.leaves += 1;
+                self.stats.max_leaf_size = self.stats.max_leaf_size.max(items.len());
+                return index;
+            }
+        };
+        let (left, right) = items.split_at_mut(middle);
+        self.build_node(left, offset, depth + 1);
+        let second = self.build_node(right, offset + middle, depth + 1);
+        let node = &mut self.nodes[index];
+        node.offset = second as u32;
+        node.count = 0;
+        node.axis = axis as u8;
+        index
+    }
+
+    /// Partitions the items by the cheapest split and returns its axis and the size of the first
+    /// half, or `None` if a leaf is cheaper
+    fn find_split(items: &mut [Item], bounds: &Bounds) -> Option<(usize, usize)> {
+        if items.len() <= 1 {
+            return None;
+        }
+        let centroids = items.iter().fold(Bounds::EMPTY, |b, i| b.grow(i.centroid));
+        let extent = centroids.max - centroids.min;
+        let area = bounds.get_surface_area().max(f32::MIN_POSITIVE);
+        let get_bin = |item: &Item, axis: usize| {
+            let relative = (item.centroid[axis] - centroids.min[axis]) / extent[axis];
+            ((relative * BIN_COUNT as f32) as usize).min(BIN_COUNT - 1)
+        };
+
+        let mut best: Option<(f32, usize, usize)> = None;
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
+            let mut bins = [(0_usize, Bounds::EMPTY); BIN_COUNT];
+            for item in items.iter() {
+                let bin = &mut bins[get_bin(item, axis)];
+                bin.0 += 1;
+                bin.1 = bin.1.union(&item.bounds);
+            }
+            // Areas and counts left of each split, then the right side while sweeping back
+            let mut left = [(0_usize, 0.0_f32); BIN_COUNT - 1];
+            let mut accumulated = (0, Bounds::EMPTY);
+            for split in 0..BIN_COUNT - 1 {
+                accumulated = (accumulated.0 + bins[split].0, accumulated.1.union(&bins[split].1));
+                left[split] = (accumulated.0, accumulated.1.get_surface_area());
+            }
+            let mut accumulated = (0, Bounds::EMPTY);
+            for split in (0..BIN_COUNT - 1).rev() {
+                accumulated = (accumulated.0 + bins[split + 1].0, accumulated.1.union(&bins[split + 1].1));
+                let (left_count, left_area) = left[split];
+                if left_count == 0 || accumulated.0 == 0 {
+                    continue;
+                }
+                let cost = TRAVERSAL_COST + (left_area * left_count as f32 + accumulated.1.get_surface_area() * accumulated.0 as f32) / area;
+                if best.is_none_or(|b| cost < b.0) {
+                    best = Some((cost, axis, split));
+                }
+            }
+        }
+
+        match best {
+            Some((cost, axis, split)) if cost < items.len() as f32 || items.len() > MAX_LEAF_SIZE => {
+                let mut middle = 0;
+                for i in 0..items.len() {
+                    if get_bin(&items[i], axis) <= split {
+                        items.swap(i, middle);
+                        middle += 1;
+                    }
+                }
+                Some((axis, middle))
+            }
+            // All centroids coincide, split in half to keep the leaves small
+            None if items.len() > MAX_LEAF_SIZE => Some((0, items.len() / 2)),
+            _ => None
+        }
+    }
+
+    /// Leaves as ranges of primitives given by offset and count with their tag, in the order the
+    /// primitives are stored
+    pub fn get_leaves(&self) -> impl Iterator<Item = (usize, usize, u32)> + '_ {
+        self.nodes.iter()
+            .filter(|n| n.count > 0)
+            .map(|n| (n.offset as usize, n.count as usize, n.tag))
+    }
+
+    /// Assigns each leaf a number from its offset and count, e.g. where data derived from its
+    /// primitives is stored. Leaves are visited in the order of `get_leaves`.
+    pub fn tag_leaves<F: FnMut(usize, usize) -> u32>(&mut self, mut tag: F) {
+        for node in self.nodes.iter_mut().filter(|n| n.count > 0) {
+            node.tag = tag(node.offset as usize, node.count as usize);
+        }
+    }
+
+    /// Visits the primitives hit by the ray front to back. `intersect` is called with the index of
+    /// a primitive and the distance of the nearest hit so far, and returns the distance of a
+    /// nearer hit on that primitive. Subtrees beyond the nearest hit are skipped.
+    pub fn traverse<F: FnMut(usize, f32) -> Option<f32>>(&self, ray: &Ray, t_max: f32, mut intersect: F) {
+        self.traverse_leaves(ray, t_max, |offset, count, _, mut t_max| {
+            let mut nearest = None;
+            for primitive in offset..offset + count {
+                if let Some(t) = intersect(primitive, t_max) {
+                    t_max = t_max.min(t);
+                    nearest = Some(t_max);
+                }
+            }
+            nearest
+        });
+    }
+
+    /// Like `traverse`, but calls `intersect` once per leaf with its offset, count and tag, so
+    /// that all primitives of a leaf can be tested together
+    pub fn traverse_leaves<F: FnMut(usize, usize, u32, f32) -> Option<f32>>(&self, ray: &Ray, mut t_max: f32, mut intersect: F) {
+        // An empty hierarchy is a single leaf without primitives, which reads like an inner node
+        if self.stats.primitives == 0 {
+            return;
+        }
+        let origin = Vec3A::from(ray.position);
+        let inverse_direction = Vec3A::from(ray.direction).recip();
+        let root = match self.nodes[0].bounds.intersect(origin, inverse_direction, t_max) {
+            Some(near) => near,
+            None => return
+        };
+        // Nodes whose box the ray enters, with the distance where it does so
+        let mut stack = [(0_u32, 0.0_f32); STACK_SIZE];
+        stack[0] = (0, root);
+        let mut size = 1;
+        while size > 0 {
+            size -= 1;
+            let (index, near) = stack[size];
+            if near > t_max {
+                continue;
+            }
+            let node = &self.nodes[index as usize];
+            if node.count > 0 {
+                if let Some(t) = intersect(node.offset as usize, node.count as usize, node.tag, t_max) {
+                    t_max = t_max.min(t);
+                }
+                continue;
+            }
+            let children = [index + 1, node.offset];
+            let hits = Bounds::intersect_pair(children.map(|c| &self.nodes[c as usize].bounds), origin, inverse_direction, t_max);
+            // Pushes the far child first so that the near one is visited next
+            let order = if ray.direction[node.axis as usize] < 0.0 { [0, 1] } else { [1, 0] };
+            for i in order {
+                if let Some(near) = hits[i] {
+                    stack[size] = (children[i], near);
+                    size += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec3, Vec3A};
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use crate::geometry::hierarchy::{Bounds, Bvh};
+    use crate::geometry::ray::Ray;
+
+    fn get_boxes() -> Vec<(Vec3, Vec3)> {
+        (0..1000)
+            .map(|i| Vec3::new((i % 100) as f32 * 2.0, (i / 100) as f32 * 2.0, 0.0))
+            .map(|p| (p - Vec3::splat(0.5), p + Vec3::splat(0.5)))
+            .collect()
+    }
+
+    #[test]
+    fn nearest_hit_with_early_exit() {
+        let boxes = get_boxes();
+        let (bvh, order) = Bvh::build(&boxes);
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!((0..1000).collect::<Vec<_>>(), sorted);
+
+        let ray = Ray::new(Vec3::new(-10.0, 6.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        let mut tests = 0;
+        let mut nearest = None;
+        bvh.traverse(&ray, f32::INFINITY, |i, t_max| {
+            tests += 1;
+            let (min, _) = boxes[order[i]];
+            let t = min.x - ray.position.x;
+            let hit = (min.y..=min.y + 1.0).contains(&ray.position.y) && t < t_max;
+            if hit { nearest = Some(order[i]); Some(t) } else { None }
+        });
+        assert_eq!(Some(300), nearest);
+        assert!(tests < 50, "{} primitives tested", tests);
+    }
+
+    #[test]
+    fn pairs_match_single_boxes() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut point = || Vec3A::new(rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0));
+        for _ in 0..1000 {
+            let boxes = [0, 1].map(|_| { let (p, q) = (point(), point()); Bounds { min: p.min(q), max: p.max(q) } });
+            let origin = point() * 2.0;
+            let inverse_direction = point().normalize().recip();
+            let expected = boxes.map(|b| b.intersect(origin, inverse_direction, 3.0));
+            assert_eq!(expected, Bounds::intersect_pair([&boxes[0], &boxes[1]], origin, inverse_direction, 3.0));
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let (bvh, _) = Bvh::build(&get_boxes());
+        let stats = bvh.get_stats();
+        assert_eq!(1000, stats.primitives);
+        assert_eq!(2 * stats.leaves - 1, stats.nodes);
+        assert!(stats.max_leaf_size <= 4 && stats.max_depth < 20, "{}", stats);
+        assert_eq!((Vec3::new(-0.5, -0.5, -0.5), Vec3::new(198.5, 18.5, 0.5)), bvh.get_bounds());
+
+        let (single, order) = Bvh::build(&[(Vec3::ZERO, Vec3::ONE)]);
+        assert_eq!(vec![0], order);
+        assert_eq!(1, single.get_stats().leaves);
+    }
+
+    #[test]
+    fn empty() {
+        let (bvh, order) = Bvh::build(&[]);
+        assert!(order.is_empty());
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        bvh.traverse(&ray, f32::INFINITY, |_, _| panic!("no primitives to test"));
+    }
+}
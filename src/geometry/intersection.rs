@@ -1,15 +1,71 @@
-use glam::Vec3;
+use glam::{Vec2, Vec3};
+use crate::geometry::ray::Ray;
 
 pub struct Intersection {
 
     pub position: Vec3,
+    /// Distance along the ray in units of its direction
+    pub t: f32,
+    /// Direction in which the texture coordinate u increases, perpendicular to the normal
     pub tangent: Vec3,
+    /// Shading normal, which may be interpolated across the surface. It lies on the same side of
+    /// the surface as the geometric normal.
     pub normal: Vec3,
-    pub distance_squared: f32
+    /// Normal of the actual surface, pointing out of closed shapes, used to move rays off of it
+    pub geometric_normal: Vec3,
+    /// Whether the ray arrives from the side the geometric normal points to
+    pub front_face: bool,
+    /// Texture coordinates
+    pub uv: Vec2,
+    /// Linear RGB color interpolated from the vertices of a mesh, if it has any
+    pub color: Option<Vec3>,
+    /// Index of the entity in the scene, set by `Scene::intersect`
+    pub entity_index: usize,
+    /// Index of the triangle in a mesh in the order the triangles were given, 0 for other surfaces
+    pub primitive_index: usize,
+    /// Index of refraction of the medium on the other side of a dielectric surface, set by the
+    /// tracer from the media the ray is in
+    pub exterior_ior: f32
 }
 
 impl Intersection {
-    pub fn new(position: Vec3, normal: Vec3, tangent: Vec3, distance_squared: f32) -> Intersection {
-        Intersection {position, normal, tangent, distance_squared}
+    /// Hit at distance `t` along the ray on a surface with the geometric `normal`
+    pub fn new(ray: &Ray, t: f32, normal: Vec3, tangent: Vec3) -> Intersection {
+        Intersection {
+            position: ray.position + ray.direction * t,
+            t,
+            tangent,
+            normal,
+            geometric_normal: normal,
+            front_face: ray.direction.dot(normal) < 0.0,
+            uv: Vec2::ZERO,
+            color: None,
+            entity_index: 0,
+            primitive_index: 0,
+            exterior_ior: 1.0
+        }
     }
-}
\ No newline at end of file
+
+    pub fn with_uv(mut self, uv: Vec2) -> Intersection {
+        self.uv = uv;
+        self
+    }
+
+    /// Turns the surface around, e.g. where a solid is cut away
+    pub fn flip(mut self) -> Intersection {
+        self.normal = -self.normal;
+        self.geometric_normal = -self.geometric_normal;
+        self.front_face = !self.front_face;
+        self
+    }
+
+    /// Shading normal on the side the ray arrived from
+    pub fn get_facing_normal(&self) -> Vec3 {
+        if self.front_face { self.normal } else { -self.normal }
+    }
+
+    /// Completes the tangent frame, pointing where the texture coordinate v increases
+    pub fn get_bitangent(&self) -> Vec3 {
+        self.normal.cross(self.tangent)
+    }
+}
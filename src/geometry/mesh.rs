@@ -1,67 +1,122 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
+use std::io::{Error, ErrorKind, Read};
+use std::path::Path;
 
-use bvh::{Point3, Vector3};
+use bvh::Point3;
 use bvh::aabb::{AABB, Bounded};
 use bvh::bounding_hierarchy::BHShape;
-use bvh::bvh::BVH;
-use glam::{Quat, Vec3};
+use glam::{Mat4, Quat, Vec2, Vec3};
 use ply_rs::{parser, ply};
 use ply_rs::ply::Property;
 
+use crate::geometry::hierarchy::{Bvh, BvhStats};
 use crate::geometry::intersection::Intersection;
 use crate::geometry::ray::Ray;
 use crate::geometry::surface::Surface;
+use crate::geometry::triangle_packet::{PACKET_WIDTH, PacketHit, TrianglePacket};
+use crate::geometry::util;
+use crate::geometry::watertight::Shear;
 
 pub struct Mesh {
-    bvh: Box<BVH>,
-    triangles: Vec<Triangle>,
+    /// Hierarchy whose leaves are tagged with the index of their first packet
+    bvh: Bvh,
+    /// Vertices of the triangles of each leaf in packets
+    packets: Vec<TrianglePacket>,
+    /// Everything else about the triangles, in the order of the leaves of the hierarchy
+    attributes: Vec<Attributes>,
+    /// Index each triangle had when the mesh was created
+    primitive_indices: Vec<u32>,
     aabb: AABB,
     node_index: usize
 }
 
 impl Mesh {
-    pub fn new(mut triangles: Vec<Triangle>) -> Mesh {
-        let bvh = BVH::build(&mut triangles);
-        let (min, max) = triangles.iter()
-            .fold((Point3::new(f32::MAX, f32::MAX, f32::MAX), Point3::new(f32::MIN, f32::MIN, f32::MIN)), |mut acc, val| {
-                let t_aabb = val.aabb();
-                acc.0 = Point3::new(acc.0.x.min(t_aabb.min.x), acc.0.y.min(t_aabb.min.y), acc.0.z.min(t_aabb.min.z));
-                acc.1 = Point3::new(acc.1.x.max(t_aabb.max.x), acc.1.y.max(t_aabb.max.y), acc.1.z.max(t_aabb.max.z));
-                acc
-            });
-        Mesh { bvh: Box::new(bvh), triangles, aabb: AABB::with_bounds(min, max), node_index: 0 }
+    pub fn new(triangles: Vec<Triangle>) -> Mesh {
+        let bounds: Vec<(Vec3, Vec3)> = triangles.iter()
+            .map(|t| (t.a.min(t.b).min(t.c), t.a.max(t.b).max(t.c)))
+            .collect();
+        let (mut bvh, order) = Bvh::build(&bounds);
+        let mut slots: Vec<Option<Triangle>> = triangles.into_iter().map(Some).collect();
+        let triangles: Vec<Triangle> = order.iter().map(|i| slots[*i].take().unwrap()).collect();
+        let primitive_indices = order.into_iter().map(|i| i as u32).collect();
+        let mut packets = vec![];
+        bvh.tag_leaves(|offset, count| {
+            let first = packets.len() as u32;
+            let vertices: Vec<[Vec3; 3]> = triangles[offset..offset + count].iter().map(|t| t.get_vertices()).collect();
+            packets.extend(TrianglePacket::pack(&vertices));
+            first
+        });
+        let attributes = triangles.into_iter().map(|t| t.attributes).collect();
+        let (min, max) = bvh.get_bounds();
+        Mesh { bvh, packets, attributes, primitive_indices, aabb: util::get_aabb(&[min, max]), node_index: 0 }
+    }
+
+    /// Vertices of all triangles in the order of the leaves
+    fn get_vertices(&self) -> impl Iterator<Item = [Vec3; 3]> + '_ {
+        self.bvh.get_leaves().flat_map(move |(_, count, first)| (0..count)
+            .map(move |i| self.packets[first as usize + i / PACKET_WIDTH].get_triangle(i % PACKET_WIDTH)))
     }
 
     pub fn get_center(&self) -> Vec3 {
         Vec3::new((self.aabb.min.x + self.aabb.max.x) as f32 / 2.0, (self.aabb.min.y + self.aabb.max.y) as f32 / 2.0, (self.aabb.min.z + self.aabb.max.z) as f32 / 2.0)
     }
 
+    /// Rotates around the center of the mesh
     pub fn rotate(self, rotation: Quat) -> Mesh {
         let mid = self.get_center();
-        let triangles = self.translate(mid * -1.0)
-            .triangles
-            .into_iter()
-            .map(|x| Triangle::new(rotation.mul_vec3(x.a), rotation.mul_vec3(x.b), rotation.mul_vec3(x.c)))
-            .collect();
-        Mesh::new(triangles).translate(mid)
+        self.transform(Mat4::from_translation(mid) * Mat4::from_quat(rotation) * Mat4::from_translation(-mid))
     }
 
+    /// Scales around the center of the mesh
     pub fn scale(self, factor: f32) -> Mesh {
         let mid = self.get_center();
-        let triangles = self.translate(mid * -1.0)
-            .triangles
-            .into_iter()
-            .map(|x| Triangle::new(x.a * factor, x.b * factor, x.c * factor))
-            .collect();
-        Mesh::new(triangles)
+        self.transform(Mat4::from_translation(mid) * Mat4::from_scale(Vec3::splat(factor)) * Mat4::from_translation(-mid))
     }
 
     pub fn translate(self, translation: Vec3) -> Mesh {
-        let triangles = self.triangles
-            .into_iter()
-            .map(|x| Triangle::new(x.a + translation, x.b + translation, x.c + translation))
+        self.transform(Mat4::from_translation(translation))
+    }
+
+    /// Applies an affine transform to the vertices, with the inverse transpose for the normals,
+    /// and rebuilds the BVH once
+    pub fn transform(self, transform: Mat4) -> Mesh {
+        let normal_transform = transform.inverse().transpose();
+        // Restores the original order to keep the primitive indices
+        let mut triangles: Vec<(u32, Triangle)> = self.primitive_indices.iter()
+            .zip(self.get_vertices().zip(self.attributes.iter()))
+            .map(|(i, ([a, b, c], attributes))| {
+                let triangle = Triangle { a, b, c, attributes: *attributes, node_index: 0 };
+                (*i, triangle.map(|p| transform.transform_point3(p), |n| normal_transform.transform_vector3(n).normalize_or_zero()))
+            })
             .collect();
-        Mesh::new(triangles)
+        triangles.sort_by_key(|(i, _)| *i);
+        Mesh::new(triangles.into_iter().map(|(_, t)| t).collect())
+    }
+
+    /// Exact bounds of the vertices after `transform`, without changing the mesh
+    pub fn get_bounds(&self, transform: Mat4) -> (Vec3, Vec3) {
+        self.get_vertices()
+            .flatten()
+            .map(|p| transform.transform_point3(p))
+            .fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(min, max), p| (min.min(p), max.max(p)))
+    }
+
+    /// Uniform scale that fits the mesh after `transform` into a cube of edge `size` around the
+    /// origin, to be composed as `fit * transform`
+    pub fn get_fit_transform(&self, transform: Mat4, size: f32) -> Mat4 {
+        let (min, max) = self.get_bounds(transform);
+        let factor = size / (max - min).max_element();
+        Mat4::from_scale(Vec3::splat(factor)) * Mat4::from_translation((min + max) * -0.5)
+    }
+
+    /// Translation that centers the mesh after `transform` on `position` horizontally and rests
+    /// its lowest point, the one with the largest z, on `position.z`
+    pub fn get_floor_transform(&self, transform: Mat4, position: Vec3) -> Mat4 {
+        let (min, max) = self.get_bounds(transform);
+        let center = (min + max) * 0.5;
+        Mat4::from_translation(Vec3::new(position.x - center.x, position.y - center.y, position.z - max.z))
     }
 }
 
@@ -83,22 +138,31 @@ impl BHShape for Mesh {
 
 impl Surface for Mesh {
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
-        let bvh_ray = bvh::ray::Ray::new(Point3::new(ray.position.x, ray.position.y, ray.position.z), Vector3::new(ray.direction.x, ray.direction.y, ray.direction.z));
-        let candidates = self.bvh.traverse(&bvh_ray, &self.triangles);
-
-        let mut min_distance = f32::INFINITY;
-        let mut result: Option<Intersection> = None;
-        for e in candidates {
-            let intersection = e.intersect(ray);
-            if let Some(i) = intersection {
-                let dist = i.distance_squared;
-                if dist < min_distance {
-                    result = Some(i);
-                    min_distance = dist;
+        // Index of the triangle in leaf order and of its packet with the hit in that packet
+        let mut nearest: Option<(usize, usize, PacketHit)> = None;
+        let shear = Shear::new(ray.direction);
+        self.bvh.traverse_leaves(ray, ray.t_max, |offset, count, first, mut t_max| {
+            let first = first as usize;
+            let mut result = None;
+            for (i, packet) in self.packets[first..first + count.div_ceil(PACKET_WIDTH)].iter().enumerate() {
+                if let Some(hit) = packet.intersect(ray, &shear, t_max) {
+                    t_max = hit.distance;
+                    nearest = Some((offset + i * PACKET_WIDTH + hit.lane, first + i, hit));
+                    result = Some(hit.distance);
                 }
             }
-        }
-        result
+            result
+        });
+        nearest.map(|(i, packet, hit)| {
+            let vertices = self.packets[packet].get_triangle(hit.lane);
+            let mut intersection = self.attributes[i].get_intersection(ray, vertices, hit.distance, hit.u, hit.v);
+            intersection.primitive_index = self.primitive_indices[i] as usize;
+            intersection
+        })
+    }
+
+    fn get_bvh_stats(&self) -> Option<BvhStats> {
+        Some(*self.bvh.get_stats())
     }
 }
 
@@ -107,17 +171,98 @@ pub struct Triangle {
     a: Vec3,
     b: Vec3,
     c: Vec3,
-    normal: Vec3,
+    attributes: Attributes,
     node_index: usize
 }
 
+/// Everything about a triangle apart from its vertices, which meshes keep in packets
+#[derive(Clone, Copy, Debug)]
+struct Attributes {
+    normal: Vec3,
+    /// Normals at `a`, `b` and `c` for smooth shading
+    vertex_normals: Option<[Vec3; 3]>,
+    /// Texture coordinates at `a`, `b` and `c`, barycentric coordinates are used without them
+    uvs: Option<[Vec2; 3]>,
+    /// Linear RGB colors at `a`, `b` and `c`
+    vertex_colors: Option<[Vec3; 3]>
+}
+
 impl Triangle {
     pub fn new(a: Vec3, b: Vec3, c: Vec3) -> Triangle {
-        let normal = (b - a).cross(c - a);
-        Triangle { a, b, c, normal, node_index: 0 }
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        let attributes = Attributes { normal, vertex_normals: None, uvs: None, vertex_colors: None };
+        Triangle { a, b, c, attributes, node_index: 0 }
+    }
+
+    /// Triangle with vertex normals, which decide on its front side regardless of the winding
+    pub fn with_normals(a: Vec3, b: Vec3, c: Vec3, normals: [Vec3; 3]) -> Triangle {
+        let mut triangle = Triangle::new(a, b, c);
+        if triangle.attributes.normal.dot(normals[0] + normals[1] + normals[2]) < 0.0 {
+            triangle.attributes.normal = -triangle.attributes.normal;
+        }
+        triangle.attributes.vertex_normals = Some(normals);
+        triangle
+    }
+
+    pub fn with_uvs(mut self, uvs: [Vec2; 3]) -> Triangle {
+        self.attributes.uvs = Some(uvs);
+        self
+    }
+
+    pub fn with_colors(mut self, colors: [Vec3; 3]) -> Triangle {
+        self.attributes.vertex_colors = Some(colors);
+        self
+    }
+
+    fn map<P: Fn(Vec3) -> Vec3, N: Fn(Vec3) -> Vec3>(&self, point: P, normal: N) -> Triangle {
+        let (a, b, c) = (point(self.a), point(self.b), point(self.c));
+        let mut triangle = match self.attributes.vertex_normals {
+            Some(n) => Triangle::with_normals(a, b, c, [normal(n[0]), normal(n[1]), normal(n[2])]),
+            None => Triangle::new(a, b, c)
+        };
+        triangle.attributes.uvs = self.attributes.uvs;
+        triangle.attributes.vertex_colors = self.attributes.vertex_colors;
+        triangle
     }
 }
 
+impl Attributes {
+    /// Direction of increasing u on the triangle, from the texture coordinates if there are any
+    fn get_tangent(&self, [a, b, c]: [Vec3; 3], normal: Vec3) -> Vec3 {
+        let e1 = b - a;
+        let e2 = c - a;
+        let direction = match self.uvs {
+            Some(uv) => {
+                let (d1, d2) = (uv[1] - uv[0], uv[2] - uv[0]);
+                let det = d1.x * d2.y - d1.y * d2.x;
+                if det != 0.0 { (e1 * d2.y - e2 * d1.y) / det } else { e1 }
+            }
+            None => e1
+        };
+        (direction - normal * normal.dot(direction)).try_normalize()
+            .unwrap_or_else(|| util::get_tangent(normal))
+    }
+}
+
+/// Vertex normals averaged from the faces around each vertex, weighted by the angle of the face
+/// at that vertex
+pub fn compute_vertex_normals(vertices: &[Vec3], faces: &[[usize; 3]]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; vertices.len()];
+    for face in faces {
+        let normal = (vertices[face[1]] - vertices[face[0]]).cross(vertices[face[2]] - vertices[face[0]]).normalize_or_zero();
+        for i in 0..3 {
+            let p = vertices[face[i]];
+            let e1 = vertices[face[(i + 1) % 3]] - p;
+            let e2 = vertices[face[(i + 2) % 3]] - p;
+            let angle = e1.angle_between(e2);
+            if angle.is_finite() {
+                normals[face[i]] += normal * angle;
+            }
+        }
+    }
+    normals.into_iter().map(|n| n.normalize_or_zero()).collect()
+}
+
 impl Bounded for Triangle {
     fn aabb(&self) -> AABB {
         let min = Point3::new(self.a.x.min(self.b.x).min(self.c.x) as f32, self.a.y.min(self.b.y).min(self.c.y) as f32, self.a.z.min(self.b.z).min(self.c.z) as f32);
@@ -136,111 +281,316 @@ impl BHShape for Triangle {
     }
 }
 
+#[derive(Debug)]
+pub enum MeshError {
+    Io(std::io::Error),
+    MissingElement(&'static str),
+    MissingProperty { element: String, property: &'static str },
+    /// A property that holds a list where a number is expected, or the other way around
+    InvalidProperty { element: String, property: String },
+    InvalidFace { face: usize, vertices: usize },
+    IndexOutOfRange { face: usize, index: i64 },
+    /// A file that is valid but holds no faces
+    EmptyMesh
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::Io(e) => write!(f, "{}", e),
+            MeshError::MissingElement(element) => write!(f, "missing element {}", element),
+            MeshError::MissingProperty { element, property } => write!(f, "{} without property {}", element, property),
+            MeshError::InvalidProperty { element, property } => write!(f, "property {} of {} has an unexpected type", property, element),
+            MeshError::InvalidFace { face, vertices } => write!(f, "face {} has only {} vertices", face, vertices),
+            MeshError::IndexOutOfRange { face, index } => write!(f, "face {} refers to missing vertex {}", face, index),
+            MeshError::EmptyMesh => write!(f, "mesh without faces")
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+impl From<std::io::Error> for MeshError {
+    fn from(e: std::io::Error) -> MeshError {
+        MeshError::Io(e)
+    }
+}
+
+#[derive(Default)]
 struct PlyTriangleReader {
     pub vertices: Vec<Vec3>,
-    pub faces: Vec<Triangle>
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Vec2>,
+    pub colors: Vec<Vec3>,
+    pub faces: Vec<[usize; 3]>
 }
 
-pub fn read_ply(mut f: File) -> Mesh {
+/// Names under which PLY exporters store texture coordinates
+const UV_PROPERTIES: [(&str, &str); 3] = [("u", "v"), ("s", "t"), ("texture_u", "texture_v")];
+
+/// Names under which PLY exporters store vertex colors
+const COLOR_PROPERTIES: [(&str, &str, &str); 2] = [("red", "green", "blue"), ("diffuse_red", "diffuse_green", "diffuse_blue")];
+
+pub fn read_ply(path: &Path) -> Result<Mesh, MeshError> {
+    parse_ply(&mut File::open(path)?)
+}
+
+/// Reads the vertices and faces of a PLY file, polygons are triangulated as fans and other
+/// elements are ignored. Normals are computed when the file has none.
+pub fn parse_ply<R: Read>(source: &mut R) -> Result<Mesh, MeshError> {
     let p = parser::Parser::<ply::DefaultElement>::new();
+    let ply = p.read_ply(source)?;
+
+    let mut reader = PlyTriangleReader::default();
+    let vertices = ply.payload.get("vertex").ok_or(MeshError::MissingElement("vertex"))?;
+    for vertex in vertices {
+        let coordinate = |name: &'static str| match vertex.get(name) {
+            Some(property) => read_num_property(property, "vertex", name),
+            None => Err(MeshError::MissingProperty { element: "vertex".to_string(), property: name })
+        };
+        reader.vertices.push(Vec3::new(coordinate("x")?, coordinate("y")?, coordinate("z")?));
+        if let (Some(x), Some(y), Some(z)) = (vertex.get("nx"), vertex.get("ny"), vertex.get("nz")) {
+            let normal = Vec3::new(read_num_property(x, "vertex", "nx")?, read_num_property(y, "vertex", "ny")?, read_num_property(z, "vertex", "nz")?);
+            reader.normals.push(normal.normalize_or_zero());
+        }
+        if let Some((u, v)) = UV_PROPERTIES.iter().find_map(|(u, v)| vertex.get(*u).zip(vertex.get(*v))) {
+            reader.uvs.push(Vec2::new(read_num_property(u, "vertex", "u")?, read_num_property(v, "vertex", "v")?));
+        }
+        if let Some((r, g, b)) = COLOR_PROPERTIES.iter().find_map(|(r, g, b)| Some((vertex.get(*r)?, vertex.get(*g)?, vertex.get(*b)?))) {
+            reader.colors.push(Vec3::new(read_color_property(r)?, read_color_property(g)?, read_color_property(b)?));
+        }
+    }
 
-    let ply = p.read_ply(&mut f);
-
-    let triangles = ply.unwrap()
-        .payload
-        .iter()
-        .fold(PlyTriangleReader { vertices: vec![], faces: vec![] }, |mut acc, val| {
-            match val {
-                ( a, d) if a == "vertex" => {
-                    acc.vertices = d.iter()
-                        .fold(acc.vertices, |mut vacc, vval| {
-                            let x= read_num_property(vval.get("x").unwrap());
-                            let y= read_num_property(vval.get("y").unwrap());
-                            let z= read_num_property(vval.get("z").unwrap());
-
-                            vacc.push(Vec3::new(x, y, z));
-                            return vacc;
-                        });
-                },
-                ( a, d) if a == "face" => {
-                    let vertices = &acc.vertices;
-                    acc.faces = d.iter()
-                        // .filter(|x| rand::thread_rng().gen::<f32>() > 0.)
-                        .fold(acc.faces, |mut vacc, vval| {
-                            if let Property::ListInt(vec) = vval.get("vertex_indices").unwrap() {
-                                assert_eq!(3, vec.len(), "We only support triangular faces");
-                                let triangle = Triangle::new(
-                                    vertices.get(*vec.get(0).unwrap() as usize).unwrap().clone(),
-                                    vertices.get(*vec.get(1).unwrap() as usize).unwrap().clone(),
-                                    vertices.get(*vec.get(2).unwrap() as usize).unwrap().clone()
-                                );
-                                vacc.push(triangle);
-                                vacc
-                            } else {
-                                panic!()
-                            }
-                        });
-                }
-                _ => panic!()
+    let faces = ply.payload.get("face").ok_or(MeshError::MissingElement("face"))?;
+    for (index, face) in faces.iter().enumerate() {
+        let indices = face.get("vertex_indices")
+            .or_else(|| face.get("vertex_index"))
+            .ok_or_else(|| MeshError::MissingProperty { element: "face".to_string(), property: "vertex_indices" })?;
+        let indices = read_index_list(indices)?;
+        if indices.len() < 3 {
+            return Err(MeshError::InvalidFace { face: index, vertices: indices.len() });
+        }
+        let indices: Vec<usize> = indices.into_iter()
+            .map(|i| if i >= 0 && (i as usize) < reader.vertices.len() { Ok(i as usize) } else { Err(MeshError::IndexOutOfRange { face: index, index: i }) })
+            .collect::<Result<_, _>>()?;
+        for i in 1..indices.len() - 1 {
+            reader.faces.push([indices[0], indices[i], indices[i + 1]]);
+        }
+    }
+    if reader.faces.is_empty() {
+        return Err(MeshError::EmptyMesh);
+    }
+
+    let normals = if reader.normals.len() == reader.vertices.len() {
+        reader.normals
+    } else {
+        compute_vertex_normals(&reader.vertices, &reader.faces)
+    };
+    let vertices = &reader.vertices;
+    let uvs = &reader.uvs;
+    let colors = &reader.colors;
+    let triangles = reader.faces.iter()
+        .map(|f| {
+            let mut triangle = Triangle::with_normals(vertices[f[0]], vertices[f[1]], vertices[f[2]], [normals[f[0]], normals[f[1]], normals[f[2]]]);
+            if uvs.len() == vertices.len() {
+                triangle = triangle.with_uvs([uvs[f[0]], uvs[f[1]], uvs[f[2]]]);
+            }
+            if colors.len() == vertices.len() {
+                triangle = triangle.with_colors([colors[f[0]], colors[f[1]], colors[f[2]]]);
             }
-            acc
+            triangle
         })
-        .faces;
-    Mesh::new(triangles)
+        .collect();
+    Ok(Mesh::new(triangles))
 }
 
-fn read_num_property(prop: &Property) -> f32{
+fn read_num_property(prop: &Property, element: &str, name: &str) -> Result<f32, MeshError> {
     match prop {
-        Property::Double(x) => *x as f32,
-        Property::Float(x) => *x,
-        Property::Int(x) => *x as f32,
-        Property::UInt(x) => *x as f32,
-        _ => panic!()
+        Property::Double(x) => Ok(*x as f32),
+        Property::Float(x) => Ok(*x),
+        Property::Int(x) => Ok(*x as f32),
+        Property::UInt(x) => Ok(*x as f32),
+        Property::Short(x) => Ok(*x as f32),
+        Property::UShort(x) => Ok(*x as f32),
+        Property::Char(x) => Ok(*x as f32),
+        Property::UChar(x) => Ok(*x as f32),
+        _ => Err(MeshError::InvalidProperty { element: element.to_string(), property: name.to_string() })
     }
 }
 
+/// Colors stored as integers are sRGB encoded bytes, floating point colors are taken as linear
+fn read_color_property(prop: &Property) -> Result<f32, MeshError> {
+    let value = read_num_property(prop, "vertex", "color")?;
+    match prop {
+        Property::Float(_) | Property::Double(_) => Ok(value),
+        _ => {
+            let encoded = (value / 255.0).clamp(0.0, 1.0);
+            Ok(if encoded <= 0.04045 { encoded / 12.92 } else { ((encoded + 0.055) / 1.055).powf(2.4) })
+        }
+    }
+}
 
-impl Surface for Triangle {
-    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+fn read_index_list(prop: &Property) -> Result<Vec<i64>, MeshError> {
+    match prop {
+        Property::ListChar(v) => Ok(v.iter().map(|i| *i as i64).collect()),
+        Property::ListUChar(v) => Ok(v.iter().map(|i| *i as i64).collect()),
+        Property::ListShort(v) => Ok(v.iter().map(|i| *i as i64).collect()),
+        Property::ListUShort(v) => Ok(v.iter().map(|i| *i as i64).collect()),
+        Property::ListInt(v) => Ok(v.iter().map(|i| *i as i64).collect()),
+        Property::ListUInt(v) => Ok(v.iter().map(|i| *i as i64).collect()),
+        _ => Err(MeshError::InvalidProperty { element: "face".to_string(), property: "vertex_indices".to_string() })
+    }
+}
 
-        let e1 = self.b - self.a;
-        let e2 = self.c - self.a;
-        let pvec = ray.direction.cross(e2);
-        let det = e1.dot(pvec);
-        if det.abs() < f32::MIN {
-            return None;
-        }
+/// Normals of adjacent faces further apart than this are not smoothed, keeps the edges of CAD parts sharp
+const CREASE_ANGLE: f32 = std::f32::consts::FRAC_PI_4;
 
-        let inv_det = 1.0 / det;
-        let tvec = ray.position - self.a;
-        let u = tvec.dot(pvec) * inv_det;
-        if u < 0.0 || u > 1.0 {
-            return None;
+/// Reads an ASCII or binary STL file. With `smooth` set, coincident vertices are welded and
+/// shaded with vertex normals, except across creases.
+pub fn read_stl(path: &Path, smooth: bool) -> Result<Mesh, MeshError> {
+    let mut data = vec![];
+    File::open(path)?.read_to_end(&mut data)?;
+    get_stl_mesh(&parse_stl(&data)?, smooth)
+}
+
+/// Mesh of the facets of an STL file, welded and smoothed as in `read_stl`
+fn get_stl_mesh(faces: &[[Vec3; 3]], smooth: bool) -> Result<Mesh, MeshError> {
+    if faces.is_empty() {
+        return Err(MeshError::EmptyMesh);
+    }
+    if !smooth {
+        return Ok(Mesh::new(faces.iter().map(|[a, b, c]| Triangle::new(*a, *b, *c)).collect()));
+    }
+    let (vertices, indices) = weld_vertices(faces);
+    let normals = compute_vertex_normals(&vertices, &indices);
+    let triangles = indices.iter()
+        .map(|f| {
+            let mut triangle = Triangle::new(vertices[f[0]], vertices[f[1]], vertices[f[2]]);
+            let face_normal = triangle.attributes.normal;
+            let corner_normals = [normals[f[0]], normals[f[1]], normals[f[2]]]
+                .map(|n| if n.angle_between(face_normal) < CREASE_ANGLE { n } else { face_normal });
+            triangle.attributes.vertex_normals = Some(corner_normals);
+            triangle
+        })
+        .collect();
+    Ok(Mesh::new(triangles))
+}
+
+/// Corners of the facets of an STL file. Binary files are recognized by their size, since some
+/// exporters start their header with `solid` as well.
+fn parse_stl(data: &[u8]) -> std::io::Result<Vec<[Vec3; 3]>> {
+    if data.len() >= 84 {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if data.len() == 84 + count * 50 || !data.starts_with(b"solid") {
+            return parse_binary_stl(data, count);
         }
+    }
+    parse_ascii_stl(&String::from_utf8_lossy(data))
+}
+
+fn parse_binary_stl(data: &[u8], count: usize) -> std::io::Result<Vec<[Vec3; 3]>> {
+    if data.len() < 84 + count * 50 {
+        return Err(Error::new(ErrorKind::InvalidData, format!("binary STL truncated, expected {} facets", count)));
+    }
+    let read = |offset: usize| f32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+    let read_vec = |offset: usize| Vec3::new(read(offset), read(offset + 4), read(offset + 8));
+    Ok((0..count)
+        .map(|i| 84 + i * 50)
+        // Skips the facet normal, which is recomputed from the winding
+        .map(|offset| [read_vec(offset + 12), read_vec(offset + 24), read_vec(offset + 36)])
+        .collect())
+}
 
-        let qvec = tvec.cross(e1);
-        let v = ray.direction.dot(qvec) * inv_det;
-        if v < 0.0 || (u + v) > 1.0 {
-            return None;
+fn parse_ascii_stl(source: &str) -> std::io::Result<Vec<[Vec3; 3]>> {
+    let mut corners = vec![];
+    for (index, line) in source.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("vertex") {
+            continue;
         }
+        let values: Vec<f32> = tokens
+            .map(|s| s.parse::<f32>())
+            .collect::<Result<_, _>>()
+            .ok()
+            .filter(|v: &Vec<f32>| v.len() == 3)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("line {}: expected three coordinates", index + 1)))?;
+        corners.push(Vec3::new(values[0], values[1], values[2]));
+    }
+    if corners.len() % 3 != 0 {
+        return Err(Error::new(ErrorKind::InvalidData, "facets need three vertices each"));
+    }
+    Ok(corners.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect())
+}
 
-        let distance = e2.dot(qvec) * inv_det;
+/// Merges corners closer than a millionth of the extent of the mesh into shared vertices
+pub fn weld_vertices(faces: &[[Vec3; 3]]) -> (Vec<Vec3>, Vec<[usize; 3]>) {
+    let (min, max) = faces.iter()
+        .flatten()
+        .fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(min, max), p| (min.min(*p), max.max(*p)));
+    let tolerance = ((max - min).max_element() * 1.0e-6).max(f32::MIN_POSITIVE);
+    let mut vertices = vec![];
+    let mut lookup: HashMap<[i64; 3], usize> = HashMap::new();
+    let indices = faces.iter()
+        .map(|face| face.map(|p| {
+            let key = ((p - min) / tolerance).round().to_array().map(|x| x as i64);
+            *lookup.entry(key).or_insert_with(|| {
+                vertices.push(p);
+                vertices.len() - 1
+            })
+        }))
+        .collect();
+    (vertices, indices)
+}
+
+impl Triangle {
+    pub fn get_vertices(&self) -> [Vec3; 3] {
+        [self.a, self.b, self.c]
+    }
+
+    /// Distance along the ray and barycentric coordinates `u`, `v` of `b` and `c` at the hit, by
+    /// the watertight test so that rays cannot slip through edges shared with other triangles
+    pub(crate) fn get_hit(&self, ray: &Ray) -> Option<(f32, f32, f32)> {
+        Shear::new(ray.direction).intersect(ray.position, self.get_vertices(), ray.t_min, ray.t_max)
+    }
 
-        if distance > f32::EPSILON {
-            let p = ray.position + ray.direction * distance;
+    pub(crate) fn get_intersection(&self, ray: &Ray, distance: f32, u: f32, v: f32) -> Intersection {
+        self.attributes.get_intersection(ray, self.get_vertices(), distance, u, v)
+    }
+}
 
-            Some(Intersection::new(p, self.normal.clone(), Vec3::new(0.0, 0.0, 0.0), distance * distance))
-        } else {
-            None
+impl Attributes {
+    /// Intersection record of a hit found by `Triangle::get_hit` or a triangle packet
+    fn get_intersection(&self, ray: &Ray, vertices: [Vec3; 3], distance: f32, u: f32, v: f32) -> Intersection {
+        let uv = match self.uvs {
+            Some(t) => t[0] * (1.0 - u - v) + t[1] * u + t[2] * v,
+            None => Vec2::new(u, v)
+        };
+        let mut intersection = Intersection::new(ray, distance, self.normal, Vec3::ZERO).with_uv(uv);
+        intersection.color = self.vertex_colors.map(|c| c[0] * (1.0 - u - v) + c[1] * u + c[2] * v);
+        if let Some(n) = self.vertex_normals {
+            let shading_normal = (n[0] * (1.0 - u - v) + n[1] * u + n[2] * v).normalize_or_zero();
+            if shading_normal != Vec3::ZERO {
+                // Interpolated normals can still tip over to the back near strongly bent vertices
+                intersection.normal = if shading_normal.dot(self.normal) < 0.0 { -shading_normal } else { shading_normal };
+            }
         }
+        intersection.tangent = self.get_tangent(vertices, intersection.normal);
+        intersection
+    }
+}
+
+impl Surface for Triangle {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.get_hit(ray).map(|(distance, u, v)| self.get_intersection(ray, distance, u, v))
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use glam::{Quat, Vec3, Vec4};
+    use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
 
+    use crate::geometry::mesh;
     use crate::geometry::mesh::Triangle;
     use crate::geometry::ray::Ray;
     use crate::geometry::surface::Surface;
@@ -249,7 +599,7 @@ mod tests {
     #[test]
     fn triangle_intersection_1() {
         let s = Triangle::new(Vec3::new(-1.0, 0.0, -1.0), Vec3::new(1.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0));
-        println!("{:?}", s.normal);
+        println!("{:?}", s.attributes.normal);
         let r = Ray::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.0, 1.0);
         let i = s.intersect(&r);
         match i {
@@ -261,7 +611,7 @@ mod tests {
     #[test]
     fn triangle_intersection_2() {
         let s = Triangle::new(Vec3::new(-1.0, 0.0, -1.0), Vec3::new(1.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0));
-        println!("{:?}", s.normal);
+        println!("{:?}", s.attributes.normal);
         let r = Ray::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 1.0, 1.0);
         let i = s.intersect(&r);
         match i {
@@ -273,7 +623,7 @@ mod tests {
     #[test]
     fn triangle_intersection_3() {
         let s = Triangle::new(Vec3::new(-1.0, 0.0, -1.0), Vec3::new(1.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0));
-        println!("{:?}", s.normal);
+        println!("{:?}", s.attributes.normal);
         let r = Ray::new(Vec3::new(1.0, -1.0, 1.0), Vec3::new(0.0, 1.0, 0.0), 1.0, 1.0);
         let i = s.intersect(&r);
         match i {
@@ -281,4 +631,299 @@ mod tests {
             Some(hit) => assert!(false)
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn interpolated_shading_normal() {
+        let n1 = Vec3::new(-1.0, -1.0, 0.0).normalize();
+        let n2 = Vec3::new(1.0, -1.0, 0.0).normalize();
+        let s = Triangle::with_normals(Vec3::new(-1.0, 0.0, -1.0), Vec3::new(1.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0), [n1, n2, Vec3::new(0.0, -1.0, 0.0)]);
+        let r = Ray::new(Vec3::new(0.0, -1.0, -0.5), Vec3::new(0.0, 1.0, 0.0), 1.0, 1.0);
+        let hit = s.intersect(&r).unwrap();
+        assert!((hit.normal - Vec3::new(0.0, -1.0, 0.0)).length() < 1.0e-5);
+        assert!((hit.geometric_normal.length() - 1.0).abs() < 1.0e-5);
+        let r = Ray::new(Vec3::new(-0.5, -1.0, -0.9), Vec3::new(0.0, 1.0, 0.0), 1.0, 1.0);
+        assert!(s.intersect(&r).unwrap().normal.x < 0.0);
+    }
+
+    #[test]
+    fn interpolated_uvs_and_tangent() {
+        let s = Triangle::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0))
+            .with_uvs([Vec2::new(0.0, 1.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0)]);
+        let r = Ray::new(Vec3::new(0.5, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0), 1.0, 1.0);
+        let hit = s.intersect(&r).unwrap();
+        assert!((hit.uv - Vec2::new(0.25, 0.75)).length() < 1.0e-5);
+        // u grows along the edge to c, which is +y
+        assert!((hit.tangent - Vec3::new(0.0, 1.0, 0.0)).length() < 1.0e-5);
+
+        let plain = Triangle::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0));
+        let hit = plain.intersect(&r).unwrap();
+        assert!((hit.uv - Vec2::new(0.25, 0.25)).length() < 1.0e-5);
+        assert_eq!(Vec3::new(1.0, 0.0, 0.0), hit.tangent);
+    }
+
+    #[test]
+    fn ascii_stl_welding() {
+        let source = "solid part
+            facet normal 0 0 1
+              outer loop
+                vertex 0 0 0
+                vertex 1 0 0
+                vertex 1 1 0
+              endloop
+            endfacet
+            facet normal 0 0 1
+              outer loop
+                vertex 0 0 0
+                vertex 1 1 0
+                vertex 0 1 0
+              endloop
+            endfacet
+            endsolid part";
+        let faces = mesh::parse_stl(source.as_bytes()).unwrap();
+        assert_eq!(2, faces.len());
+        assert_eq!(Vec3::new(0.0, 1.0, 0.0), faces[1][2]);
+        let (vertices, indices) = mesh::weld_vertices(&faces);
+        assert_eq!(4, vertices.len());
+        assert_eq!([0, 2, 3], indices[1]);
+    }
+
+    /// ASCII STL of the facets, without facet normals as some exporters write them
+    fn to_ascii_stl(faces: &[[Vec3; 3]]) -> String {
+        let facets: String = faces.iter()
+            .map(|f| format!("facet normal 0 0 0\nouter loop\n{}endloop\nendfacet\n", f.iter().map(|p| format!("vertex {} {} {}\n", p.x, p.y, p.z)).collect::<String>()))
+            .collect();
+        format!("solid test\n{}endsolid test\n", facets)
+    }
+
+    #[test]
+    fn smooth_stl_keeps_creases() {
+        // Unit cube with outward facing triangles, two per side
+        let mut cube = vec![];
+        for axis in 0..3 {
+            for side in [0.0, 1.0] {
+                let corner = |u: f32, v: f32| {
+                    let mut p = Vec3::ZERO;
+                    p[axis] = side;
+                    p[(axis + 1) % 3] = u;
+                    p[(axis + 2) % 3] = v;
+                    p
+                };
+                let quad = [corner(0.0, 0.0), corner(1.0, 0.0), corner(1.0, 1.0), corner(0.0, 1.0)];
+                let quad = if side > 0.0 { quad } else { [quad[0], quad[3], quad[2], quad[1]] };
+                cube.push([quad[0], quad[1], quad[2]]);
+                cube.push([quad[0], quad[2], quad[3]]);
+            }
+        }
+        let faces = mesh::parse_stl(to_ascii_stl(&cube).as_bytes()).unwrap();
+        assert_eq!(8, mesh::weld_vertices(&faces).0.len());
+        let mesh = mesh::get_stl_mesh(&faces, true).unwrap();
+        // Corners of the cube meet at 90°, so every vertex falls back to the face normal
+        assert!(mesh.attributes.iter().all(|a| a.vertex_normals.unwrap().iter().all(|n| *n == a.normal)));
+        let near_edge = Ray::new(Vec3::new(0.98, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0), 550.0, 1.0);
+        assert_eq!(Vec3::Z, mesh.intersect(&near_edge).unwrap().normal);
+
+        // Two panels folded by about 23° along the y axis are shaded smoothly across the fold
+        let h = 0.2;
+        let fold = [
+            [Vec3::new(-1.0, 0.0, h), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)],
+            [Vec3::new(-1.0, 0.0, h), Vec3::new(0.0, 1.0, 0.0), Vec3::new(-1.0, 1.0, h)],
+            [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, h), Vec3::new(1.0, 1.0, h)],
+            [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, h), Vec3::new(0.0, 1.0, 0.0)]
+        ];
+        let faces = mesh::parse_stl(to_ascii_stl(&fold).as_bytes()).unwrap();
+        assert_eq!(6, mesh::weld_vertices(&faces).0.len());
+        let mesh = mesh::get_stl_mesh(&faces, true).unwrap();
+        let at_fold = Ray::new(Vec3::new(-0.01, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0), 550.0, 1.0);
+        let hit = mesh.intersect(&at_fold).unwrap();
+        assert!((hit.geometric_normal - Vec3::new(h, 0.0, 1.0).normalize()).length() < 1.0e-5);
+        assert!(hit.normal.x.abs() < 0.01 && hit.normal.z > 0.99, "{:?}", hit.normal);
+        let flat = mesh::get_stl_mesh(&faces, false).unwrap();
+        assert_eq!(hit.geometric_normal, flat.intersect(&at_fold).unwrap().normal);
+    }
+
+    #[test]
+    fn binary_stl() {
+        // Header starting with "solid" like some exporters write it
+        let mut data = b"solid binary".to_vec();
+        data.resize(80, 0);
+        data.extend(1u32.to_le_bytes());
+        for value in [0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0] {
+            data.extend(value.to_le_bytes());
+        }
+        data.extend(0u16.to_le_bytes());
+        let faces = mesh::parse_stl(&data).unwrap();
+        assert_eq!(vec![[Vec3::ZERO, Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0)]], faces);
+        data[..5].copy_from_slice(b"part ");
+        assert!(mesh::parse_stl(&data[..100]).is_err());
+    }
+
+    const QUAD_PLY: &str = "ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar uint vertex_indices
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 255 0 0
+1 0 0 255 0 0
+1 1 0 0 0 255
+0 1 0 0 0 255
+4 0 1 2 3
+0 1
+";
+
+    #[test]
+    fn ply_polygons_and_colors() {
+        let mesh = mesh::parse_ply(&mut QUAD_PLY.as_bytes()).unwrap();
+        assert_eq!(2, mesh.attributes.len());
+        let r = Ray::new(Vec3::new(0.5, 0.9, -1.0), Vec3::new(0.0, 0.0, 1.0), 1.0, 1.0);
+        let color = mesh.intersect(&r).unwrap().color.unwrap();
+        assert!((color - Vec3::new(0.1, 0.0, 0.9)).length() < 1.0e-5);
+    }
+
+    #[test]
+    fn ply_errors() {
+        let out_of_range = QUAD_PLY.replace("4 0 1 2 3", "4 0 1 2 7");
+        assert!(matches!(mesh::parse_ply(&mut out_of_range.as_bytes()), Err(mesh::MeshError::IndexOutOfRange { face: 0, index: 7 })));
+        let line = QUAD_PLY.replace("4 0 1 2 3", "2 0 1");
+        assert!(matches!(mesh::parse_ply(&mut line.as_bytes()), Err(mesh::MeshError::InvalidFace { face: 0, vertices: 2 })));
+        assert!(matches!(mesh::read_ply(std::path::Path::new("missing.ply")), Err(mesh::MeshError::Io(_))));
+        let empty = QUAD_PLY.replace("element face 1", "element face 0").replace("4 0 1 2 3\n", "");
+        assert!(matches!(mesh::parse_ply(&mut empty.as_bytes()), Err(mesh::MeshError::EmptyMesh)));
+        assert!(matches!(mesh::read_stl(std::path::Path::new("missing.stl"), false), Err(mesh::MeshError::Io(_))));
+    }
+
+    #[test]
+    fn composed_transforms() {
+        let triangles = vec![
+            Triangle::with_normals(Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), [Vec3::Z; 3]),
+            Triangle::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 4.0))
+        ];
+        let m = mesh::Mesh::new(triangles);
+        let rotation = Mat4::from_rotation_x(std::f32::consts::FRAC_PI_2);
+        let transform = m.get_fit_transform(rotation, 2.0) * rotation;
+        let transform = m.get_floor_transform(transform, Vec3::new(1.0, 2.0, 10.0)) * transform;
+        let m = m.transform(transform);
+        let (min, max) = m.get_bounds(Mat4::IDENTITY);
+        assert!((max - min - Vec3::new(1.0, 2.0, 0.5)).length() < 1.0e-5, "{:?} {:?}", min, max);
+        assert!((max.z - 10.0).abs() < 1.0e-5);
+        assert!(((min + max) * 0.5 - Vec3::new(1.0, 2.0, 9.75)).length() < 1.0e-5);
+        let normals = m.attributes.iter().find_map(|a| a.vertex_normals).unwrap();
+        assert!((normals[0] - Vec3::new(0.0, -1.0, 0.0)).length() < 1.0e-5);
+
+        let scaled = m.scale(2.0);
+        assert!((scaled.get_center() - Vec3::new(1.0, 2.0, 9.75)).length() < 1.0e-5);
+    }
+
+    #[test]
+    fn angle_weighted_vertex_normals() {
+        // Corner of a cube: three faces meeting at the origin at right angles
+        let vertices = [Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::Z];
+        let faces = [[0, 2, 1], [0, 1, 3], [0, 3, 2]];
+        let normals = mesh::compute_vertex_normals(&vertices, &faces);
+        assert!((normals[0] - Vec3::splat(-1.0).normalize()).length() < 1.0e-5);
+    }
+
+    /// Irregular grid in the plane z = 1, each cell split into two triangles
+    fn get_grid(size: usize) -> (Vec<Vec3>, Vec<Triangle>) {
+        let vertex = |i: usize, j: usize| Vec3::new(i as f32 * 0.37 + (j % 3) as f32 * 0.011, j as f32 * 0.29 + (i % 5) as f32 * 0.007, 1.0);
+        let vertices = (0..=size).flat_map(|j| (0..=size).map(move |i| vertex(i, j))).collect();
+        let triangles = (0..size).flat_map(|j| (0..size).flat_map(move |i| [
+            Triangle::new(vertex(i, j), vertex(i + 1, j), vertex(i + 1, j + 1)),
+            Triangle::new(vertex(i, j), vertex(i + 1, j + 1), vertex(i, j + 1))
+        ])).collect();
+        (vertices, triangles)
+    }
+
+    #[test]
+    fn no_leaks_through_grid_edges_and_vertices() {
+        let (vertices, triangles) = get_grid(12);
+        let edges: Vec<(Vec3, Vec3)> = triangles.iter()
+            .flat_map(|t| [(t.a, t.b), (t.b, t.c), (t.c, t.a)])
+            .collect();
+        let m = mesh::Mesh::new(triangles);
+        let origins = [Vec3::new(2.0, 1.5, -3.0), Vec3::new(-1.3, 7.1, -0.4), Vec3::new(0.1, 0.2, 5.0)];
+        let inner = |p: Vec3| p.x > 0.1 && p.y > 0.1 && p.x < 4.3 && p.y < 3.4;
+        let targets = vertices.into_iter()
+            .chain(edges.iter().flat_map(|(a, b)| [0.5_f32, 0.1, 0.77].map(|f| a.lerp(*b, f))))
+            .filter(|p| inner(*p));
+        let mut rays = 0;
+        for target in targets {
+            for origin in origins {
+                let ray = Ray::new(origin, target - origin, 550.0, 1.0);
+                let hit = m.intersect(&ray);
+                assert!(hit.is_some(), "ray from {:?} leaks through {:?}", origin, target);
+                assert!((hit.unwrap().position - target).length() < 1.0e-4);
+                rays += 1;
+            }
+        }
+        assert!(rays > 2000);
+    }
+
+    #[test]
+    fn no_leaks_out_of_closed_mesh() {
+        // Octahedron subdivided and pushed onto the unit sphere
+        let mut faces: Vec<[Vec3; 3]> = [Vec3::X, Vec3::Y, -Vec3::X, -Vec3::Y].iter()
+            .zip([Vec3::Y, -Vec3::X, -Vec3::Y, Vec3::X].iter())
+            .flat_map(|(a, b)| [[*a, *b, Vec3::Z], [*b, *a, -Vec3::Z]])
+            .collect();
+        for _ in 0..4 {
+            faces = faces.iter()
+                .flat_map(|[a, b, c]| {
+                    let (ab, bc, ca) = ((*a + *b).normalize(), (*b + *c).normalize(), (*c + *a).normalize());
+                    [[*a, ab, ca], [ab, *b, bc], [ca, bc, *c], [ab, bc, ca]]
+                })
+                .collect();
+        }
+        let m = mesh::Mesh::new(faces.iter().map(|[a, b, c]| Triangle::new(*a, *b, *c)).collect());
+        let origin = Vec3::new(0.013, -0.021, 0.007);
+        let directions = faces.iter()
+            .flat_map(|[a, b, c]| [*a, *b, *c, (*a + *b) * 0.5, (*b + *c) * 0.5])
+            .map(|p| p - origin)
+            .chain((0..2000).map(|i| {
+                let (z, angle) = (1.0 - (i as f32 + 0.5) / 1000.0, i as f32 * 2.399_963);
+                let r = (1.0 - z * z).sqrt();
+                Vec3::new(r * angle.cos(), r * angle.sin(), z)
+            }));
+        for direction in directions {
+            let ray = Ray::new(origin, direction, 550.0, 1.0);
+            assert!(m.intersect(&ray).is_some(), "ray along {:?} leaks", direction);
+        }
+    }
+
+    #[test]
+    fn empty_mesh() {
+        let m = mesh::Mesh::new(vec![]);
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), 550.0, 1.0);
+        assert!(m.intersect(&ray).is_none());
+    }
+
+    #[test]
+    fn primitive_index_and_front_face() {
+        let (_, triangles) = get_grid(8);
+        let target = (triangles[37].a + triangles[37].b + triangles[37].c) / 3.0;
+        let m = mesh::Mesh::new(triangles).translate(Vec3::new(0.0, 0.0, 2.0));
+        let target = target + Vec3::new(0.0, 0.0, 2.0);
+
+        let below = Ray::new(target - Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0), 550.0, 1.0);
+        let hit = m.intersect(&below).unwrap();
+        assert_eq!(37, hit.primitive_index);
+        assert!(hit.front_face == (hit.geometric_normal.z < 0.0));
+        assert!((hit.t - 5.0).abs() < 1.0e-5);
+        assert!(hit.get_facing_normal().z < 0.0);
+
+        let above = Ray::new(target + Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 550.0, 1.0);
+        let hit = m.intersect(&above).unwrap();
+        assert_eq!(37, hit.primitive_index);
+        assert!(hit.front_face == (hit.geometric_normal.z > 0.0));
+        assert!(hit.get_facing_normal().z > 0.0);
+    }
+}
@@ -5,4 +5,15 @@ pub mod ray;
 pub mod plane;
 pub mod circle;
 pub mod mesh;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod cone;
+pub mod rectangle;
+pub mod torus;
+pub mod quadric;
+pub mod hierarchy;
+pub mod triangle_packet;
+pub mod watertight;
+pub(crate) mod polynomial;
 pub(crate) mod util;
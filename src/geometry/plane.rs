@@ -3,7 +3,8 @@ use bvh::bounding_hierarchy::BHShape;
 use crate::geometry::intersection::Intersection;
 use crate::geometry::ray::Ray;
 use crate::geometry::surface::Surface;
-use glam::Vec3;
+use glam::{Vec2, Vec3};
+use crate::geometry::util;
 
 pub struct Plane {
     normal: Vec3,
@@ -40,15 +41,14 @@ impl Surface for Plane {
             return None;
         }
         let t = -self.normal.dot(origin) / d;
-        if t <= 0.0 {
+        if !ray.contains(t) {
             return None;
         }
         let pos = ray.position + ray.direction * t;
-        let normal = if d >= 0.0 {
-            self.normal * -1.0
-        } else {
-            self.normal
-        };
-        Some(Intersection::new(pos, normal, Vec3::new(0.0, 0.0, 0.0), t * t))
+        // Planar mapping in scene units, with a tangent frame fixed to the plane
+        let tangent = util::get_tangent(self.normal);
+        let bitangent = self.normal.cross(tangent);
+        let offset = pos - self.position;
+        Some(Intersection::new(ray, t, self.normal, tangent).with_uv(Vec2::new(offset.dot(tangent), offset.dot(bitangent))))
     }
 }
\ No newline at end of file
@@ -0,0 +1,117 @@
+use std::f64::consts::PI;
+
+/// Real roots of `a x² + b x + c`
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return if b != 0.0 { vec![-c / b] } else { vec![] };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        return vec![0.0];
+    }
+    vec![q / a, c / q]
+}
+
+/// Largest real root of the monic cubic `x³ + a x² + b x + c`
+fn get_largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    let q = (a * a - 3.0 * b) / 9.0;
+    let r = (2.0 * a * a * a - 9.0 * a * b + 27.0 * c) / 54.0;
+    if r * r < q * q * q {
+        let theta = (r / (q * q * q).sqrt()).clamp(-1.0, 1.0).acos();
+        let s = -2.0 * q.sqrt();
+        (0..3)
+            .map(|k| s * ((theta + 2.0 * PI * k as f64) / 3.0).cos() - a / 3.0)
+            .fold(f64::NEG_INFINITY, f64::max)
+    } else {
+        let big_a = -r.signum() * (r.abs() + (r * r - q * q * q).sqrt()).cbrt();
+        let big_b = if big_a != 0.0 { q / big_a } else { 0.0 };
+        big_a + big_b - a / 3.0
+    }
+}
+
+/// Real roots of `c[0] x⁴ + c[1] x³ + c[2] x² + c[3] x + c[4]` by Ferrari's method, polished with
+/// a few Newton iterations on the original polynomial
+pub fn solve_quartic(c: [f64; 5]) -> Vec<f64> {
+    if c[0] == 0.0 {
+        return vec![];
+    }
+    let (a, b, cc, d) = (c[1] / c[0], c[2] / c[0], c[3] / c[0], c[4] / c[0]);
+    // Depressed quartic y⁴ + p y² + q y + r with x = y - a / 4
+    let a2 = a * a;
+    let p = b - 3.0 * a2 / 8.0;
+    let q = cc - a * b / 2.0 + a2 * a / 8.0;
+    let r = d - a * cc / 4.0 + a2 * b / 16.0 - 3.0 * a2 * a2 / 256.0;
+
+    let mut roots = vec![];
+    if q.abs() < 1.0e-12 {
+        for y2 in solve_quadratic(1.0, p, r) {
+            if y2 >= 0.0 {
+                roots.push(y2.sqrt());
+                roots.push(-y2.sqrt());
+            }
+        }
+    } else {
+        let m = get_largest_cubic_root(p, p * p / 4.0 - r, -q * q / 8.0);
+        if m <= 0.0 {
+            return vec![];
+        }
+        let s = (2.0 * m).sqrt();
+        roots.extend(solve_quadratic(1.0, -s, p / 2.0 + m + q / (2.0 * s)));
+        roots.extend(solve_quadratic(1.0, s, p / 2.0 + m - q / (2.0 * s)));
+    }
+    roots.into_iter()
+        .map(|y| polish(&c, y - a / 4.0))
+        .collect()
+}
+
+fn polish(c: &[f64; 5], mut x: f64) -> f64 {
+    for _ in 0..3 {
+        let f = (((c[0] * x + c[1]) * x + c[2]) * x + c[3]) * x + c[4];
+        let df = ((4.0 * c[0] * x + 3.0 * c[1]) * x + 2.0 * c[2]) * x + c[3];
+        if df == 0.0 {
+            break;
+        }
+        x -= f / df;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::polynomial::solve_quartic;
+
+    fn sorted(mut roots: Vec<f64>) -> Vec<f64> {
+        roots.sort_by(|a, b| a.total_cmp(b));
+        roots
+    }
+
+    #[test]
+    fn four_real_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let roots = sorted(solve_quartic([1.0, -10.0, 35.0, -50.0, 24.0]));
+        assert_eq!(4, roots.len());
+        for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0, 4.0]) {
+            assert!((root - expected).abs() < 1.0e-9, "{:?}", roots);
+        }
+    }
+
+    #[test]
+    fn biquadratic_and_no_roots() {
+        // (x² - 1)(x² - 4)
+        let roots = sorted(solve_quartic([1.0, 0.0, -5.0, 0.0, 4.0]));
+        assert_eq!(vec![-2.0, -1.0, 1.0, 2.0], roots);
+        assert!(solve_quartic([1.0, 0.0, 1.0, 0.0, 1.0]).is_empty());
+    }
+
+    #[test]
+    fn two_real_roots() {
+        // (x - 0.5)(x + 3)(x² + 1)
+        let roots = sorted(solve_quartic([1.0, 2.5, -0.5, 2.5, -1.5]));
+        assert_eq!(2, roots.len());
+        assert!((roots[0] + 3.0).abs() < 1.0e-9 && (roots[1] - 0.5).abs() < 1.0e-9);
+    }
+}
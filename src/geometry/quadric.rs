@@ -0,0 +1,158 @@
+use bvh::aabb::{AABB, Bounded};
+use bvh::bounding_hierarchy::BHShape;
+use std::f32::consts::PI;
+use glam::{Mat4, Vec2, Vec3, Vec4};
+use crate::geometry::intersection::Intersection;
+use crate::geometry::polynomial;
+use crate::geometry::ray::Ray;
+use crate::geometry::surface::Surface;
+use crate::geometry::util;
+
+/// Surface where pᵀ Q p = 0 for the homogeneous point p = (x, y, z, 1) and a symmetric matrix Q,
+/// clipped to a box in its local frame. The inside is where pᵀ Q p < 0.
+pub struct Quadric {
+    matrix: Mat4,
+    min: Vec3,
+    max: Vec3,
+    transform: Mat4,
+    inverse: Mat4,
+    node_index: usize
+}
+
+impl Quadric {
+    pub fn new(matrix: Mat4, min: Vec3, max: Vec3) -> Quadric {
+        Quadric { matrix, min, max, transform: Mat4::IDENTITY, inverse: Mat4::IDENTITY, node_index: 0 }
+    }
+
+    /// Ellipsoid around the origin with the given radii along the axes
+    pub fn ellipsoid(radii: Vec3) -> Quadric {
+        let matrix = Mat4::from_diagonal(Vec4::new(1.0 / (radii.x * radii.x), 1.0 / (radii.y * radii.y), 1.0 / (radii.z * radii.z), -1.0));
+        Quadric::new(matrix, -radii, radii)
+    }
+
+    /// Paraboloid z = (x² + y²) / 4f with its vertex at the origin, opening towards +z with the
+    /// focus at (0, 0, f). It is cut off at `radius` from the axis.
+    pub fn paraboloid(focal_length: f32, radius: f32) -> Quadric {
+        let mut matrix = Mat4::from_diagonal(Vec4::new(1.0, 1.0, 0.0, 0.0));
+        matrix.z_axis.w = -2.0 * focal_length;
+        matrix.w_axis.z = -2.0 * focal_length;
+        let depth = radius * radius / (4.0 * focal_length);
+        Quadric::new(matrix, Vec3::new(-radius, -radius, depth.min(0.0)), Vec3::new(radius, radius, depth.max(0.0)))
+    }
+
+    /// Hyperboloid of one sheet (x² + y²) / a² - z² / c² = 1 around the z axis, `height` long
+    pub fn hyperboloid(a: f32, c: f32, height: f32) -> Quadric {
+        let matrix = Mat4::from_diagonal(Vec4::new(1.0 / (a * a), 1.0 / (a * a), -1.0 / (c * c), -1.0));
+        let half = height * 0.5;
+        let radius = a * (1.0 + half * half / (c * c)).sqrt();
+        Quadric::new(matrix, Vec3::new(-radius, -radius, -half), Vec3::new(radius, radius, half))
+    }
+
+    /// Places the quadric in the scene, e.g. with `Mat4::from_rotation_translation`
+    pub fn with_transform(mut self, transform: Mat4) -> Quadric {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+        self
+    }
+
+    fn contains(&self, p: Vec3) -> bool {
+        let epsilon = 1.0e-4;
+        p.cmpge(self.min - epsilon).all() && p.cmple(self.max + epsilon).all()
+    }
+}
+
+impl Bounded for Quadric {
+    fn aabb(&self) -> AABB {
+        let corners: Vec<Vec3> = (0..8)
+            .map(|i| Vec3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z }))
+            .map(|c| self.transform.transform_point3(c))
+            .collect();
+        util::get_aabb(&corners)
+    }
+}
+
+impl BHShape for Quadric {
+    fn set_bh_node_index(&mut self, index: usize) {
+        self.node_index = index;
+    }
+
+    fn bh_node_index(&self) -> usize {
+        self.node_index
+    }
+}
+
+impl Surface for Quadric {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.intersect_all(ray).into_iter().next()
+    }
+
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let origin = self.inverse.transform_point3(ray.position);
+        let direction = self.inverse.transform_vector3(ray.direction);
+        let o = origin.extend(1.0);
+        let d = direction.extend(0.0);
+        let qd = self.matrix * d;
+        let a = d.dot(qd) as f64;
+        let b = 2.0 * o.dot(qd) as f64;
+        let c = o.dot(self.matrix * o) as f64;
+        let mut distances: Vec<f32> = polynomial::solve_quadratic(a, b, c)
+            .into_iter()
+            .map(|t| t as f32)
+            .filter(|t| ray.contains(*t) && self.contains(origin + direction * *t))
+            .collect();
+        distances.sort_by(|a, b| a.total_cmp(b));
+        distances.into_iter()
+            .map(|t| {
+                let local = origin + direction * t;
+                let gradient = (self.matrix * local.extend(1.0)).truncate();
+                let normal = self.inverse.transpose().transform_vector3(gradient).normalize();
+                // Cylindrical mapping: u around the local z axis, v along the clip range
+                let around = self.transform.transform_vector3(Vec3::new(-local.y, local.x, 0.0));
+                let tangent = (around - normal * normal.dot(around)).try_normalize()
+                    .unwrap_or_else(|| util::get_tangent(normal));
+                let u = (local.y.atan2(local.x) + PI) / (2.0 * PI);
+                let v = ((local.z - self.min.z) / (self.max.z - self.min.z)).clamp(0.0, 1.0);
+                Intersection::new(ray, t, normal, tangent).with_uv(Vec2::new(u, v))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Mat4, Vec3};
+    use crate::geometry::quadric::Quadric;
+    use crate::geometry::ray::Ray;
+    use crate::geometry::surface::Surface;
+    use crate::geometry::util;
+
+    #[test]
+    fn paraboloid_focuses_parallel_rays() {
+        let mirror = Quadric::paraboloid(2.0, 3.0);
+        for x in [0.5_f32, 1.0, 2.5] {
+            let ray = Ray::new(Vec3::new(x, 0.3, 10.0), Vec3::new(0.0, 0.0, -1.0), 550.0, 1.0);
+            let hit = mirror.intersect(&ray).unwrap();
+            let reflected = util::reflect(ray.direction, hit.normal);
+            // The reflected ray passes through the focus
+            let to_focus = Vec3::new(0.0, 0.0, 2.0) - hit.position;
+            assert!(reflected.cross(to_focus).length() < 1.0e-4, "{} {:?}", x, reflected);
+            assert!(reflected.dot(to_focus) > 0.0);
+        }
+        let outside = Ray::new(Vec3::new(3.5, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0), 550.0, 1.0);
+        assert!(mirror.intersect(&outside).is_none());
+    }
+
+    #[test]
+    fn transformed_ellipsoid() {
+        let ellipsoid = Quadric::ellipsoid(Vec3::new(1.0, 2.0, 3.0))
+            .with_transform(Mat4::from_translation(Vec3::new(5.0, 0.0, 0.0)));
+        let ray = Ray::new(Vec3::new(5.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 550.0, 1.0);
+        let hits = ellipsoid.intersect_all(&ray);
+        assert_eq!(2, hits.len());
+        assert!((hits[0].position - Vec3::new(5.0, -2.0, 0.0)).length() < 1.0e-5);
+        assert!((hits[0].normal - Vec3::new(0.0, -1.0, 0.0)).length() < 1.0e-5);
+    }
+}
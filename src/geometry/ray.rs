@@ -1,15 +1,80 @@
 use glam::Vec3;
 
+#[derive(Clone, Copy, Debug)]
 pub struct Ray {
     pub position: Vec3,
     pub direction: Vec3,
     pub wavelength: f32,
-    pub strength: f32
+    pub strength: f32,
+    /// Hits count only strictly between `t_min` and `t_max`, in units of `direction`
+    pub t_min: f32,
+    pub t_max: f32
 }
 
 impl Ray {
 
     pub fn new(position: Vec3, direction: Vec3, wavelength: f32, strength: f32) -> Ray {
-        Ray { position, direction, wavelength, strength }
+        Ray { position, direction, wavelength, strength, t_min: 0.0, t_max: f32::INFINITY }
     }
-}
\ No newline at end of file
+
+    pub fn with_interval(mut self, t_min: f32, t_max: f32) -> Ray {
+        self.t_min = t_min;
+        self.t_max = t_max;
+        self
+    }
+
+    /// Moves the origin off the surface with `geometric_normal` it starts on, to the side the ray
+    /// leaves to
+    pub fn with_offset(mut self, geometric_normal: Vec3) -> Ray {
+        let normal = if self.direction.dot(geometric_normal) < 0.0 { -geometric_normal } else { geometric_normal };
+        self.position = offset_origin(self.position, normal);
+        self
+    }
+
+    /// Whether a hit at distance `t` lies inside the interval of the ray
+    pub fn contains(&self, t: f32) -> bool {
+        t > self.t_min && t < self.t_max
+    }
+}
+
+/// Moves a point on a surface along `normal` by a few units in the last place of its coordinates,
+/// enough to cover the rounding error of the hit at any scale. Close to the origin, where the
+/// error no longer shrinks with the coordinates, a small fixed offset is used instead. After
+/// Wächter and Binder, "A Fast and Robust Method for Avoiding Self-Intersection".
+pub fn offset_origin(position: Vec3, normal: Vec3) -> Vec3 {
+    const ORIGIN: f32 = 1.0 / 32.0;
+    const FLOAT_SCALE: f32 = 1.0 / 65536.0;
+    const INT_SCALE: f32 = 256.0;
+    let mut result = position;
+    for axis in 0..3 {
+        let p = position[axis];
+        result[axis] = if p.abs() < ORIGIN {
+            p + FLOAT_SCALE * normal[axis]
+        } else {
+            // The bit pattern of negative numbers grows with their magnitude
+            let offset = (INT_SCALE * normal[axis]) as i32;
+            f32::from_bits((p.to_bits() as i32).wrapping_add(if p < 0.0 { -offset } else { offset }) as u32)
+        };
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::geometry::ray::{offset_origin, Ray};
+
+    #[test]
+    fn offset_scales_with_position() {
+        for position in [Vec3::new(0.001, -0.002, 0.0), Vec3::new(3.0, -7.5, 1.0), Vec3::new(-25000.0, 1.0e6, 4.0e-3)] {
+            for normal in [Vec3::X, -Vec3::Y, Vec3::new(0.6, -0.8, 0.0)] {
+                let offset = offset_origin(position, normal) - position;
+                assert!(offset.dot(normal) > 0.0, "{:?} {:?}", position, normal);
+                assert!(offset.length() < 1.0e-4 * position.abs().max_element().max(1.0));
+            }
+        }
+        let ray = Ray::new(Vec3::new(5.0, 5.0, 10.0), Vec3::new(0.3, 0.0, -1.0), 550.0, 1.0).with_offset(Vec3::Z);
+        assert!(ray.position.z < 10.0);
+        assert!(!ray.contains(0.0) && ray.contains(1.0e-30) && ray.with_interval(1.0, 2.0).contains(1.5));
+    }
+}
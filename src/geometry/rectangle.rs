@@ -0,0 +1,100 @@
+use bvh::aabb::{AABB, Bounded};
+use bvh::bounding_hierarchy::BHShape;
+use glam::{Vec2, Vec3};
+use crate::geometry::intersection::Intersection;
+use crate::geometry::ray::Ray;
+use crate::geometry::surface::Surface;
+use crate::geometry::util;
+
+/// Flat quad spanned by the edges `u` and `v` from the corner at `position`. Like `Circle` it is
+/// two sided and the usual shape of area lights.
+pub struct Rectangle {
+    position: Vec3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    node_index: usize
+}
+
+impl Rectangle {
+    pub fn new(position: Vec3, u: Vec3, v: Vec3) -> Rectangle {
+        Rectangle { position, u, v, normal: u.cross(v).normalize(), node_index: 0 }
+    }
+
+    /// Rectangle of `width` along `u` and `height` along `v` centered on `position`
+    pub fn centered(position: Vec3, u: Vec3, v: Vec3, width: f32, height: f32) -> Rectangle {
+        let u = u.normalize() * width;
+        let v = v.normalize() * height;
+        Rectangle::new(position - (u + v) * 0.5, u, v)
+    }
+
+    pub fn get_area(&self) -> f32 {
+        self.u.cross(self.v).length()
+    }
+}
+
+impl Bounded for Rectangle {
+    fn aabb(&self) -> AABB {
+        util::get_aabb(&[self.position, self.position + self.u, self.position + self.v, self.position + self.u + self.v])
+    }
+}
+
+impl BHShape for Rectangle {
+    fn set_bh_node_index(&mut self, index: usize) {
+        self.node_index = index;
+    }
+
+    fn bh_node_index(&self) -> usize {
+        self.node_index
+    }
+}
+
+impl Surface for Rectangle {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let d = self.normal.dot(ray.direction);
+        if d == 0.0 {
+            return None;
+        }
+        let t = self.normal.dot(self.position - ray.position) / d;
+        if !ray.contains(t) {
+            return None;
+        }
+        let pos = ray.position + ray.direction * t;
+        // Coordinates along the edges, which need not be perpendicular
+        let n = self.u.cross(self.v);
+        let w = n / n.length_squared();
+        let offset = pos - self.position;
+        let alpha = w.dot(offset.cross(self.v));
+        let beta = w.dot(self.u.cross(offset));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        // The edge coordinates double as texture coordinates
+        let tangent = self.u.normalize();
+        Some(Intersection::new(ray, t, self.normal, tangent).with_uv(Vec2::new(alpha, beta)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec2, Vec3};
+    use crate::geometry::ray::Ray;
+    use crate::geometry::rectangle::Rectangle;
+    use crate::geometry::surface::Surface;
+
+    #[test]
+    fn hits_inside_only() {
+        let rectangle = Rectangle::centered(Vec3::ZERO, Vec3::X, Vec3::Y, 2.0, 1.0);
+        assert_eq!(2.0, rectangle.get_area());
+        let hit = Ray::new(Vec3::new(0.9, 0.4, -1.0), Vec3::new(0.0, 0.0, 1.0), 550.0, 1.0);
+        let i = rectangle.intersect(&hit).unwrap();
+        assert_eq!(Vec3::new(0.9, 0.4, 0.0), i.position);
+        // The ray hits the back of the rectangle
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), i.normal);
+        assert!(!i.front_face);
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), i.get_facing_normal());
+        assert!((i.uv - Vec2::new(0.95, 0.9)).length() < 1.0e-6);
+        let miss = Ray::new(Vec3::new(0.9, 0.6, -1.0), Vec3::new(0.0, 0.0, 1.0), 550.0, 1.0);
+        assert!(rectangle.intersect(&miss).is_none());
+    }
+}
@@ -1,66 +1,85 @@
+use bvh::Point3;
 use bvh::aabb::{AABB, Bounded};
 use bvh::bounding_hierarchy::BHShape;
 use crate::geometry::intersection::Intersection;
-use glam::Vec3;
+use glam::{Vec2, Vec3};
+use std::f32::consts::PI;
 use super::surface::Surface;
 use super::ray::Ray;
 
 pub struct Sphere {
     pub position: Vec3,
     pub radius: f32,
-    radius_squared: f32
+    radius_squared: f32,
+    node_index: usize
 }
 
 impl Sphere {
     pub fn new(position: Vec3, radius: f32) -> Sphere {
-        Sphere { position, radius, radius_squared: radius * radius }
+        Sphere { position, radius, radius_squared: radius * radius, node_index: 0 }
     }
 }
 
 impl Bounded for Sphere {
     fn aabb(&self) -> AABB {
-        todo!()
+        let min = self.position - Vec3::splat(self.radius);
+        let max = self.position + Vec3::splat(self.radius);
+        AABB::with_bounds(Point3::new(min.x, min.y, min.z), Point3::new(max.x, max.y, max.z))
     }
 }
 
 impl BHShape for Sphere {
-    fn set_bh_node_index(&mut self, _: usize) {
-        todo!()
+    fn set_bh_node_index(&mut self, index: usize) {
+        self.node_index = index;
     }
 
     fn bh_node_index(&self) -> usize {
-        todo!()
+        self.node_index
     }
 }
 
-impl Surface for Sphere {
-    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+impl Sphere {
+    /// Both distances along the ray where it crosses the sphere, nearest first
+    fn get_distances(&self, ray: &Ray) -> Option<(f32, f32)> {
         let a = ray.direction.length_squared();
         let c_offset = ray.position - self.position;
         let b = 2.0 * ray.direction.dot(c_offset);
-        let c = &c_offset.length_squared() - self.radius_squared;
+        let c = c_offset.length_squared() - self.radius_squared;
         let discriminant = b * b - 4.0 * a * c;
-
         if discriminant < 0.0 {
-            return Option::None;
+            return None;
         }
-
         let d = discriminant.sqrt();
-        let t1 = 0.5 * (-b + d) / a;
-        let t2 = 0.5 * (-b - d) / a;
-
-        let t = t1.min(t2);
-
-        if t <= 0.0 {
-            return Option::None;
-        }
+        Some((0.5 * (-b - d) / a, 0.5 * (-b + d) / a))
+    }
 
+    fn get_intersection(&self, ray: &Ray, t: f32) -> Intersection {
         let pos = ray.position + ray.direction * t;
         let normal = (pos - self.position).normalize();
-        let tangent = Vec3::new(0.0, 1.0, 0.0).cross(normal).normalize();
-        let inter = Intersection::new(pos, normal, tangent, t * t);
+        // Longitude around the z axis and latitude from the zenith at -z
+        let u = (normal.y.atan2(normal.x) + PI) / (2.0 * PI);
+        let v = (-normal.z).clamp(-1.0, 1.0).acos() / PI;
+        let tangent = Vec3::new(-normal.y, normal.x, 0.0).try_normalize().unwrap_or(Vec3::X);
+        Intersection::new(ray, t, normal, tangent).with_uv(Vec2::new(u, v))
+    }
+}
 
-        Option::Some(inter)
+impl Surface for Sphere {
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        match self.get_distances(ray) {
+            None => vec![],
+            Some((t1, t2)) => [t1, t2].iter()
+                .filter(|t| ray.contains(**t))
+                .map(|t| self.get_intersection(ray, *t))
+                .collect()
+        }
+    }
+
+    /// Nearest crossing inside the ray interval, which is the exit for rays starting inside
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let (t1, t2) = self.get_distances(ray)?;
+        let t = [t1, t2].iter().copied().find(|t| ray.contains(*t))?;
+        Some(self.get_intersection(ray, t))
     }
 }
 
@@ -69,7 +88,7 @@ mod tests {
     use crate::geometry::ray::Ray;
     use crate::geometry::sphere::Sphere;
     use crate::geometry::surface::Surface;
-    use glam::Vec3;
+    use glam::{Vec2, Vec3};
 
     #[test]
     fn sphere_intersection_center_pos_y() {
@@ -142,6 +161,26 @@ mod tests {
         }
     }
     #[test]
+    fn sphere_intersection_inside_and_interval() {
+        let s = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 2.0);
+        let inside = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.0, 1.0);
+        assert_eq!(Vec3::new(0.0, 2.0, 0.0), s.intersect(&inside).unwrap().position);
+        let r = Ray::new(Vec3::new(0.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.0, 1.0);
+        assert_eq!(Vec3::new(0.0, 2.0, 0.0), s.intersect(&r.with_interval(3.0, 10.0)).unwrap().position);
+        assert!(s.intersect(&r.with_interval(0.0, 3.0)).is_none());
+        assert_eq!(1, s.intersect_all(&r.with_interval(4.0, 10.0)).len());
+    }
+    #[test]
+    fn spherical_mapping() {
+        let s = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0);
+        let top = s.intersect(&Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 1.0, 1.0)).unwrap();
+        assert_eq!(0.0, top.uv.y);
+        let side = s.intersect(&Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 1.0, 1.0)).unwrap();
+        assert!((side.uv - Vec2::new(0.75, 0.5)).length() < 1.0e-6);
+        assert!((side.tangent - Vec3::new(-1.0, 0.0, 0.0)).length() < 1.0e-6);
+        assert!(side.tangent.dot(side.normal).abs() < 1.0e-6);
+    }
+    #[test]
     fn sphere_miss_x_axis() {
         let s = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 10.0);
         let r = Ray::new(Vec3::new(10.1, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 1.0, 1.0);
@@ -1,9 +1,33 @@
 use bvh::aabb::Bounded;
 use bvh::bounding_hierarchy::BHShape;
+use super::hierarchy::BvhStats;
 use super::intersection::Intersection;
 use super::ray::Ray;
 
 pub trait Surface: Sync + Send + Bounded + BHShape {
 
     fn intersect(&self, ray: &Ray) -> Option<Intersection>;
-}
\ No newline at end of file
+
+    /// All points where the ray crosses the surface inside its interval, ordered by distance.
+    /// Found by repeatedly moving the start of the interval past the nearest intersection, closed
+    /// surfaces should override this with an exact solution.
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let mut intersections: Vec<Intersection> = vec![];
+        let mut current = *ray;
+        while let Some(i) = self.intersect(&current) {
+            current.t_min = i.t;
+            intersections.push(i);
+            if intersections.len() >= MAX_INTERSECTIONS {
+                break;
+            }
+        }
+        intersections
+    }
+
+    /// Statistics of the acceleration structure of surfaces that have one
+    fn get_bvh_stats(&self) -> Option<BvhStats> {
+        None
+    }
+}
+
+const MAX_INTERSECTIONS: usize = 64;
\ No newline at end of file
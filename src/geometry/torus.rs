@@ -0,0 +1,137 @@
+use bvh::aabb::{AABB, Bounded};
+use bvh::bounding_hierarchy::BHShape;
+use std::f32::consts::PI;
+use glam::{Quat, Vec2, Vec3};
+use crate::geometry::cylinder::get_disc_bounds;
+use crate::geometry::intersection::Intersection;
+use crate::geometry::polynomial;
+use crate::geometry::ray::Ray;
+use crate::geometry::surface::Surface;
+use crate::geometry::util;
+
+/// Ring around `axis` through `position`, with the tube of `minor_radius` at `major_radius` from
+/// the center
+pub struct Torus {
+    position: Vec3,
+    axis: Vec3,
+    major_radius: f32,
+    minor_radius: f32,
+    rotation: Quat,
+    node_index: usize
+}
+
+impl Torus {
+    pub fn new(position: Vec3, axis: Vec3, major_radius: f32, minor_radius: f32) -> Torus {
+        let axis = axis.normalize();
+        Torus { position, axis, major_radius, minor_radius, rotation: Quat::from_rotation_arc(Vec3::Z, axis), node_index: 0 }
+    }
+
+    /// Outward normal at a point on the surface in the local frame
+    fn get_normal(&self, p: Vec3) -> Vec3 {
+        let r2 = self.major_radius * self.major_radius;
+        let g = p.length_squared() - r2 - self.minor_radius * self.minor_radius;
+        Vec3::new(p.x * g, p.y * g, p.z * (g + 2.0 * r2)).normalize()
+    }
+}
+
+impl Bounded for Torus {
+    fn aabb(&self) -> AABB {
+        let offset = self.axis * self.minor_radius;
+        let [a, b] = get_disc_bounds(self.position + offset, self.axis, self.major_radius + self.minor_radius);
+        let [c, d] = get_disc_bounds(self.position - offset, self.axis, self.major_radius + self.minor_radius);
+        util::get_aabb(&[a, b, c, d])
+    }
+}
+
+impl BHShape for Torus {
+    fn set_bh_node_index(&mut self, index: usize) {
+        self.node_index = index;
+    }
+
+    fn bh_node_index(&self) -> usize {
+        self.node_index
+    }
+}
+
+impl Surface for Torus {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.intersect_all(ray).into_iter().next()
+    }
+
+    /// Solves (|p|² - R² - r²)² = 4 R² (r² - z²) in double precision, with the origin moved close
+    /// to the torus to keep the coefficients well conditioned
+    fn intersect_all(&self, ray: &Ray) -> Vec<Intersection> {
+        let inverse = self.rotation.inverse();
+        let o = inverse.mul_vec3(ray.position - self.position).as_dvec3();
+        let d = inverse.mul_vec3(ray.direction).as_dvec3();
+        let bound = (self.major_radius + self.minor_radius) as f64;
+        let start = (-o.dot(d) / d.length_squared()).max(0.0) - 2.0 * bound / d.length();
+        let start = start.max(0.0);
+        let o = o + d * start;
+
+        let r2 = (self.major_radius as f64).powi(2);
+        let minor2 = (self.minor_radius as f64).powi(2);
+        let dd = d.length_squared();
+        let e = o.length_squared() - r2 - minor2;
+        let f = o.dot(d);
+        let coefficients = [
+            dd * dd,
+            4.0 * dd * f,
+            2.0 * dd * e + 4.0 * f * f + 4.0 * r2 * d.z * d.z,
+            4.0 * f * e + 8.0 * r2 * o.z * d.z,
+            e * e - 4.0 * r2 * (minor2 - o.z * o.z)
+        ];
+        let mut distances: Vec<f64> = polynomial::solve_quartic(coefficients)
+            .into_iter()
+            .map(|t| t + start)
+            .filter(|t| ray.contains(*t as f32))
+            .collect();
+        distances.sort_by(|a, b| a.total_cmp(b));
+        distances.into_iter()
+            .map(|t| {
+                let local = (o + d * (t - start)).as_vec3();
+                let normal = self.get_normal(local);
+                let tangent = Vec3::Z.cross(local).try_normalize().unwrap_or(Vec3::X);
+                // u goes around the axis, v around the tube starting on the outer equator
+                let radial = local.x.hypot(local.y) - self.major_radius;
+                let u = (local.y.atan2(local.x) + PI) / (2.0 * PI);
+                let v = local.z.atan2(radial).rem_euclid(2.0 * PI) / (2.0 * PI);
+                let t = t as f32;
+                Intersection::new(ray, t, self.rotation.mul_vec3(normal), self.rotation.mul_vec3(tangent))
+                    .with_uv(Vec2::new(u, v))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::geometry::ray::Ray;
+    use crate::geometry::surface::Surface;
+    use crate::geometry::torus::Torus;
+
+    #[test]
+    fn crosses_the_ring_four_times() {
+        let torus = Torus::new(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), 2.0, 0.5);
+        let ray = Ray::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 550.0, 1.0);
+        let hits = torus.intersect_all(&ray);
+        let xs: Vec<f32> = hits.iter().map(|h| h.position.x).collect();
+        assert_eq!(4, xs.len());
+        for (x, expected) in xs.iter().zip([-2.5, -1.5, 1.5, 2.5]) {
+            assert!((x - expected).abs() < 1.0e-4, "{:?}", xs);
+        }
+        assert!((hits[0].normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1.0e-4);
+        assert!((hits[1].normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1.0e-4);
+    }
+
+    #[test]
+    fn through_the_hole() {
+        let torus = Torus::new(Vec3::ZERO, Vec3::new(0.0, 1.0, 0.0), 2.0, 0.5);
+        let ray = Ray::new(Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 550.0, 1.0);
+        assert!(torus.intersect(&ray).is_none());
+        let tube = Ray::new(Vec3::new(2.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 550.0, 1.0);
+        let hit = torus.intersect(&tube).unwrap();
+        assert!((hit.position.y + 0.5).abs() < 1.0e-4);
+    }
+}
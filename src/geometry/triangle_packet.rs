@@ -0,0 +1,165 @@
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+use glam::Vec3;
+use crate::geometry::ray::Ray;
+use crate::geometry::watertight::Shear;
+
+pub const PACKET_WIDTH: usize = 4;
+
+/// Nearest hit in a packet: the lane, the distance along the ray and the barycentric coordinates
+/// of the second and third vertex
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PacketHit {
+    pub lane: usize,
+    pub distance: f32,
+    pub u: f32,
+    pub v: f32
+}
+
+/// Up to four triangles in structure of arrays layout, one array per axis of each vertex
+#[derive(Clone, Copy, Debug)]
+pub struct TrianglePacket {
+    vertices: [[[f32; PACKET_WIDTH]; 3]; 3],
+    count: usize
+}
+
+impl TrianglePacket {
+    pub fn new(triangles: &[[Vec3; 3]]) -> TrianglePacket {
+        assert!(triangles.len() <= PACKET_WIDTH, "at most {} triangles per packet", PACKET_WIDTH);
+        let mut vertices = [[[0.0; PACKET_WIDTH]; 3]; 3];
+        for (lane, triangle) in triangles.iter().enumerate() {
+            for (vertex, p) in triangle.iter().enumerate() {
+                for axis in 0..3 {
+                    vertices[vertex][axis][lane] = p[axis];
+                }
+            }
+        }
+        TrianglePacket { vertices, count: triangles.len() }
+    }
+
+    /// Vertices of the triangle in `lane`
+    pub fn get_triangle(&self, lane: usize) -> [Vec3; 3] {
+        let get = |v: &[[f32; PACKET_WIDTH]; 3]| Vec3::new(v[0][lane], v[1][lane], v[2][lane]);
+        [get(&self.vertices[0]), get(&self.vertices[1]), get(&self.vertices[2])]
+    }
+
+    /// Splits triangles into packets, the last one possibly only partially filled
+    pub fn pack(triangles: &[[Vec3; 3]]) -> Vec<TrianglePacket> {
+        triangles.chunks(PACKET_WIDTH).map(TrianglePacket::new).collect()
+    }
+
+    /// Nearest hit of all lanes between `ray.t_min` and `t_max`, with the same watertight test
+    /// as `Triangle::intersect`. `shear` belongs to the ray and is shared by all packets.
+    pub fn intersect(&self, ray: &Ray, shear: &Shear, t_max: f32) -> Option<PacketHit> {
+        #[cfg(target_arch = "x86_64")]
+        unsafe { self.intersect_sse(ray, shear, t_max) }
+        #[cfg(not(target_arch = "x86_64"))]
+        self.intersect_scalar(ray, shear, t_max)
+    }
+
+    /// Tests one lane after the other, for targets without SSE
+    pub fn intersect_scalar(&self, ray: &Ray, shear: &Shear, t_max: f32) -> Option<PacketHit> {
+        let mut nearest: Option<PacketHit> = None;
+        for lane in 0..self.count {
+            let triangle = self.get_triangle(lane);
+            let limit = nearest.map_or(t_max, |n| n.distance);
+            if let Some((distance, u, v)) = shear.intersect(ray.position, triangle, ray.t_min, limit) {
+                nearest = Some(PacketHit { lane, distance, u, v });
+            }
+        }
+        nearest
+    }
+
+    /// Tests all four lanes at once, SSE is part of every x86_64 target. Rays exactly through an
+    /// edge or vertex need double precision and go through `intersect_scalar`.
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn intersect_sse(&self, ray: &Ray, shear: &Shear, t_max: f32) -> Option<PacketHit> {
+        let [kx, ky, kz] = shear.axes;
+        // Vertex relative to the ray origin in the sheared space
+        let transform = |v: &[[f32; PACKET_WIDTH]; 3]| {
+            let z = _mm_sub_ps(_mm_loadu_ps(v[kz].as_ptr()), _mm_set1_ps(ray.position[kz]));
+            let x = _mm_sub_ps(_mm_sub_ps(_mm_loadu_ps(v[kx].as_ptr()), _mm_set1_ps(ray.position[kx])), _mm_mul_ps(_mm_set1_ps(shear.x), z));
+            let y = _mm_sub_ps(_mm_sub_ps(_mm_loadu_ps(v[ky].as_ptr()), _mm_set1_ps(ray.position[ky])), _mm_mul_ps(_mm_set1_ps(shear.y), z));
+            (x, y, _mm_mul_ps(_mm_set1_ps(shear.z), z))
+        };
+        let edge = |p: (__m128, __m128, __m128), q: (__m128, __m128, __m128)| _mm_sub_ps(_mm_mul_ps(p.0, q.1), _mm_mul_ps(p.1, q.0));
+
+        let (a, b, c) = (transform(&self.vertices[0]), transform(&self.vertices[1]), transform(&self.vertices[2]));
+        let (u, v, w) = (edge(c, b), edge(a, c), edge(b, a));
+        let zero = _mm_setzero_ps();
+        let lanes = (1 << self.count) - 1;
+        let on_edge = _mm_or_ps(_mm_or_ps(_mm_cmpeq_ps(u, zero), _mm_cmpeq_ps(v, zero)), _mm_cmpeq_ps(w, zero));
+        if _mm_movemask_ps(on_edge) & lanes != 0 {
+            return self.intersect_scalar(ray, shear, t_max);
+        }
+
+        let negative = _mm_or_ps(_mm_or_ps(_mm_cmplt_ps(u, zero), _mm_cmplt_ps(v, zero)), _mm_cmplt_ps(w, zero));
+        let positive = _mm_or_ps(_mm_or_ps(_mm_cmpgt_ps(u, zero), _mm_cmpgt_ps(v, zero)), _mm_cmpgt_ps(w, zero));
+        let det = _mm_add_ps(_mm_add_ps(u, v), w);
+        let distance = _mm_div_ps(_mm_add_ps(_mm_add_ps(_mm_mul_ps(u, a.2), _mm_mul_ps(v, b.2)), _mm_mul_ps(w, c.2)), det);
+        let mask = _mm_andnot_ps(
+            _mm_and_ps(negative, positive),
+            _mm_and_ps(
+                _mm_cmpneq_ps(det, zero),
+                _mm_and_ps(_mm_cmpgt_ps(distance, _mm_set1_ps(ray.t_min)), _mm_cmplt_ps(distance, _mm_set1_ps(t_max)))
+            )
+        );
+        let hits = _mm_movemask_ps(mask) & lanes;
+        if hits == 0 {
+            return None;
+        }
+        let mut distances = [0.0_f32; PACKET_WIDTH];
+        _mm_storeu_ps(distances.as_mut_ptr(), distance);
+        let lane = (0..PACKET_WIDTH)
+            .filter(|lane| hits & (1 << lane) != 0)
+            .min_by(|x, y| distances[*x].total_cmp(&distances[*y]))?;
+        let (mut us, mut vs) = ([0.0_f32; PACKET_WIDTH], [0.0_f32; PACKET_WIDTH]);
+        _mm_storeu_ps(us.as_mut_ptr(), _mm_div_ps(v, det));
+        _mm_storeu_ps(vs.as_mut_ptr(), _mm_div_ps(w, det));
+        Some(PacketHit { lane, distance: distances[lane], u: us[lane], v: vs[lane] })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use crate::geometry::mesh::Triangle;
+    use crate::geometry::ray::Ray;
+    use crate::geometry::triangle_packet::TrianglePacket;
+    use crate::geometry::watertight::Shear;
+
+    #[test]
+    fn packets_match_single_triangles() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut point = || Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+        let triangles: Vec<Triangle> = (0..23).map(|_| Triangle::new(point(), point(), point())).collect();
+        let vertices: Vec<[Vec3; 3]> = triangles.iter().map(|t| t.get_vertices()).collect();
+        let packets = TrianglePacket::pack(&vertices);
+        assert_eq!(6, packets.len());
+
+        for _ in 0..500 {
+            let ray = Ray::new(point() * 3.0, point().normalize(), 550.0, 1.0);
+            let expected = triangles.iter()
+                .enumerate()
+                .filter_map(|(i, t)| t.get_hit(&ray).map(|hit| (i, hit)))
+                .min_by(|x, y| x.1.0.total_cmp(&y.1.0));
+            for scalar in [false, true] {
+                let mut nearest: Option<(usize, (f32, f32, f32))> = None;
+                let shear = Shear::new(ray.direction);
+                for (p, packet) in packets.iter().enumerate() {
+                    let t_max = nearest.map_or(f32::INFINITY, |n| n.1.0);
+                    let hit = if scalar { packet.intersect_scalar(&ray, &shear, t_max) } else { packet.intersect(&ray, &shear, t_max) };
+                    if let Some(hit) = hit {
+                        nearest = Some((4 * p + hit.lane, (hit.distance, hit.u, hit.v)));
+                    }
+                }
+                assert_eq!(expected.map(|e| e.0), nearest.map(|n| n.0));
+                if let (Some((_, e)), Some((_, n))) = (expected, nearest) {
+                    assert!((e.0 - n.0).abs() < 1.0e-5 && (e.1 - n.1).abs() < 1.0e-5 && (e.2 - n.2).abs() < 1.0e-5);
+                }
+            }
+        }
+    }
+}
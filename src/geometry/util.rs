@@ -1,3 +1,5 @@
+use bvh::Point3;
+use bvh::aabb::AABB;
 use glam::Vec3;
 
 pub fn reflect(a: Vec3, b: Vec3) -> Vec3 {
@@ -16,4 +18,28 @@ pub fn rotate_towards(a: Vec3, b: Vec3) -> Vec3 {
     let a1 = Vec3::new(0.0, 0.0, 1.0).cross(b).normalize();
     let a2 = a1.cross(b).normalize();
     return a1 * a.x + a2 * a.y + b * a.z;
-}
\ No newline at end of file
+}
+
+/// Unit vector perpendicular to `normal`, continuous over most of the sphere
+pub fn get_tangent(normal: Vec3) -> Vec3 {
+    let reference = if normal.x.abs() < 0.9 { Vec3::X } else { Vec3::Y };
+    (reference - normal * normal.dot(reference)).normalize()
+}
+
+/// Smallest box containing all `points`
+pub fn get_aabb(points: &[Vec3]) -> AABB {
+    let min = points.iter().fold(Vec3::splat(f32::INFINITY), |a, p| a.min(*p));
+    let max = points.iter().fold(Vec3::splat(f32::NEG_INFINITY), |a, p| a.max(*p));
+    AABB::with_bounds(Point3::new(min.x, min.y, min.z), Point3::new(max.x, max.y, max.z))
+}
+
+/// Refracts `a` at a surface with normal `n` facing against it, `eta` is the ratio of the
+/// incident to the transmitted index of refraction. Returns `None` on total internal reflection.
+pub fn refract(a: Vec3, n: Vec3, eta: f32) -> Option<Vec3> {
+    let cosi = -a.dot(n);
+    let sin_tsqr = eta * eta * (1.0 - cosi * cosi);
+    if sin_tsqr > 1.0 {
+        return None;
+    }
+    Some(a * eta + n * (eta * cosi - (1.0 - sin_tsqr).sqrt()))
+}
@@ -0,0 +1,65 @@
+use glam::Vec3;
+
+/// Permutation and shear that map a ray direction onto +z, after Woop, Benthin and Wald,
+/// "Watertight Ray/Triangle Intersection". Rays through an edge shared by two triangles hit at
+/// least one of them, since both evaluate the same edge function.
+#[derive(Clone, Copy, Debug)]
+pub struct Shear {
+    /// Axes that become x, y and z, z being the largest component of the direction
+    pub axes: [usize; 3],
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+
+impl Shear {
+    pub fn new(direction: Vec3) -> Shear {
+        let abs = direction.abs();
+        let kz = if abs.x > abs.y && abs.x > abs.z { 0 } else if abs.y > abs.z { 1 } else { 2 };
+        let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+        // Keeps the winding of the triangles
+        if direction[kz] < 0.0 {
+            std::mem::swap(&mut kx, &mut ky);
+        }
+        Shear { axes: [kx, ky, kz], x: direction[kx] / direction[kz], y: direction[ky] / direction[kz], z: 1.0 / direction[kz] }
+    }
+
+    /// Vertex relative to the ray origin in the sheared space, where the ray runs along +z
+    pub fn apply(&self, p: Vec3) -> Vec3 {
+        let [kx, ky, kz] = self.axes;
+        Vec3::new(p[kx] - self.x * p[kz], p[ky] - self.y * p[kz], self.z * p[kz])
+    }
+
+    /// Distance along the ray and barycentric coordinates of the second and third vertex, for hits
+    /// strictly between `t_min` and `t_max`. Both sides of the triangle are hit.
+    pub fn intersect(&self, origin: Vec3, [a, b, c]: [Vec3; 3], t_min: f32, t_max: f32) -> Option<(f32, f32, f32)> {
+        let (a, b, c) = (self.apply(a - origin), self.apply(b - origin), self.apply(c - origin));
+        let (u, v, w) = get_edge_functions(a, b, c);
+        if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+            return None;
+        }
+        let det = u + v + w;
+        if det == 0.0 {
+            return None;
+        }
+        let distance = (u * a.z + v * b.z + w * c.z) / det;
+        if distance > t_min && distance < t_max {
+            Some((distance, v / det, w / det))
+        } else {
+            None
+        }
+    }
+}
+
+/// Twice the signed areas of the projected triangles opposite to each vertex. Values of exactly
+/// zero are recomputed in double precision to decide on which side of an edge the ray passes.
+fn get_edge_functions(a: Vec3, b: Vec3, c: Vec3) -> (f32, f32, f32) {
+    let u = c.x * b.y - c.y * b.x;
+    let v = a.x * c.y - a.y * c.x;
+    let w = b.x * a.y - b.y * a.x;
+    if u != 0.0 && v != 0.0 && w != 0.0 {
+        return (u, v, w);
+    }
+    let edge = |p: Vec3, q: Vec3| (p.x as f64 * q.y as f64 - p.y as f64 * q.x as f64) as f32;
+    (edge(c, b), edge(a, c), edge(b, a))
+}
@@ -0,0 +1,228 @@
+use std::f32::consts::FRAC_PI_2;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use bvh::aabb::Bounded;
+use glam::{Mat4, Quat, Vec2, Vec3};
+use gltf::camera::Projection;
+use gltf::material::AlphaMode;
+use gltf::mesh::Mode;
+use crate::camera::camera::{Camera, FieldOfView};
+use crate::camera::orthographic::OrthographicCamera;
+use crate::camera::perspective::PerspectiveCamera;
+use crate::entity::Entity;
+use crate::geometry::mesh::{Mesh, Triangle};
+use crate::material::diffuse::DiffuseRgbMaterial;
+use crate::material::dispersion::Dispersion;
+use crate::material::glass::DielectricMaterial;
+use crate::material::glossy::GlossyMaterial;
+use crate::material::material::{Material, Radiator};
+use crate::material::spectrum_radiator::RgbRadiator;
+use crate::scene::Scene;
+
+/// glTF is y up while the scenes here have their zenith at -z
+fn get_axis_conversion() -> Mat4 {
+    Mat4::from_rotation_x(-FRAC_PI_2)
+}
+
+fn to_io_error(error: gltf::Error) -> Error {
+    match error {
+        gltf::Error::Io(e) => e,
+        e => Error::new(ErrorKind::InvalidData, e.to_string())
+    }
+}
+
+/// Imports the default scene of a `.gltf` or `.glb` file with its meshes, materials and the first
+/// camera. Without a camera the scene is viewed from the front.
+pub fn read_gltf(path: &Path) -> std::io::Result<Scene> {
+    let (document, buffers, _) = gltf::import(path).map_err(to_io_error)?;
+    build_scene(&document, &buffers)
+}
+
+fn build_scene(document: &gltf::Document, buffers: &[gltf::buffer::Data]) -> std::io::Result<Scene> {
+    let scene = document.default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "no scene"))?;
+    let mut importer = Importer { buffers, entities: vec![], camera: None };
+    for node in scene.nodes() {
+        importer.add_node(&node, get_axis_conversion())?;
+    }
+    let camera = match importer.camera {
+        Some(camera) => camera,
+        None => get_default_camera(&importer.entities)
+    };
+    Ok(Scene::new(importer.entities, camera))
+}
+
+struct Importer<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    entities: Vec<Entity>,
+    camera: Option<Box<dyn Camera>>
+}
+
+impl Importer<'_> {
+    fn add_node(&mut self, node: &gltf::Node, parent: Mat4) -> std::io::Result<()> {
+        let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                if let Some(entity) = self.read_primitive(&primitive, transform)? {
+                    self.entities.push(entity);
+                }
+            }
+        }
+        if let (Some(camera), None) = (node.camera(), self.camera.as_ref()) {
+            self.camera = Some(convert_camera(&camera, transform));
+        }
+        for child in node.children() {
+            self.add_node(&child, transform)?;
+        }
+        Ok(())
+    }
+
+    fn read_primitive(&self, primitive: &gltf::Primitive, transform: Mat4) -> std::io::Result<Option<Entity>> {
+        if primitive.mode() != Mode::Triangles {
+            return Ok(None);
+        }
+        let reader = primitive.reader(|buffer| self.buffers.get(buffer.index()).map(|data| &data.0[..]));
+        let positions: Vec<Vec3> = match reader.read_positions() {
+            Some(positions) => positions.map(|p| transform.transform_point3(Vec3::from(p))).collect(),
+            None => return Ok(None)
+        };
+        let normal_transform = transform.inverse().transpose();
+        let normals: Option<Vec<Vec3>> = reader.read_normals()
+            .map(|normals| normals.map(|n| normal_transform.transform_vector3(Vec3::from(n)).normalize_or_zero()).collect());
+        let uvs: Option<Vec<Vec2>> = reader.read_tex_coords(0)
+            .map(|uvs| uvs.into_f32().map(Vec2::from).collect());
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..positions.len()).collect()
+        };
+        if indices.iter().any(|i| *i >= positions.len()) {
+            return Err(Error::new(ErrorKind::InvalidData, "vertex index out of range"));
+        }
+        let triangles: Vec<Triangle> = indices.chunks_exact(3)
+            .map(|f| {
+                let (a, b, c) = (positions[f[0]], positions[f[1]], positions[f[2]]);
+                let triangle = match &normals {
+                    Some(n) => Triangle::with_normals(a, b, c, [n[f[0]], n[f[1]], n[f[2]]]),
+                    None => Triangle::new(a, b, c)
+                };
+                match &uvs {
+                    Some(t) => triangle.with_uvs([t[f[0]], t[f[1]], t[f[2]]]),
+                    None => triangle
+                }
+            })
+            .collect();
+        if triangles.is_empty() {
+            return Ok(None);
+        }
+        let material = primitive.material();
+        let entity = Entity::dark(Box::new(Mesh::new(triangles)), convert_material(&material));
+        Ok(Some(match convert_emission(&material) {
+            Some(radiator) => entity.with_radiator(radiator),
+            None => entity
+        }))
+    }
+}
+
+/// Approximates a metallic-roughness material: transmissive or blended materials become clear
+/// dielectrics, metals reflect glossily and everything else is diffuse in the base color
+fn convert_material(material: &gltf::Material) -> Box<dyn Material> {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, alpha] = pbr.base_color_factor();
+    let transmission = material.transmission().map(|t| t.transmission_factor()).unwrap_or(0.0);
+    if transmission > 0.5 || (material.alpha_mode() == AlphaMode::Blend && alpha < 0.5) {
+        let ior = material.ior().unwrap_or(1.5);
+        return Box::new(DielectricMaterial::new(Dispersion::Constant(ior)));
+    }
+    let diffuse = Box::new(DiffuseRgbMaterial::new(Vec3::new(r, g, b)));
+    let metallic = pbr.metallic_factor().clamp(0.0, 1.0);
+    if metallic > 0.0 {
+        let roughness = pbr.roughness_factor().clamp(0.0, 1.0);
+        Box::new(GlossyMaterial::new(1.0 - metallic * (1.0 - roughness), diffuse))
+    } else {
+        diffuse
+    }
+}
+
+fn convert_emission(material: &gltf::Material) -> Option<Box<dyn Radiator>> {
+    let emission = Vec3::from(material.emissive_factor());
+    if emission.max_element() <= 0.0 {
+        return None;
+    }
+    Some(Box::new(RgbRadiator::new(emission, material.emissive_strength().unwrap_or(1.0))))
+}
+
+/// glTF cameras look along their local -z with y at the top of the image
+fn convert_camera(camera: &gltf::Camera, transform: Mat4) -> Box<dyn Camera> {
+    let (_, rotation, position) = transform.to_scale_rotation_translation();
+    let orientation = rotation * Quat::from_rotation_x(-FRAC_PI_2);
+    match camera.projection() {
+        Projection::Perspective(perspective) => {
+            let mut result = PerspectiveCamera::new(position, orientation, FieldOfView::Vertical(perspective.yfov()));
+            if let Some(aspect_ratio) = perspective.aspect_ratio() {
+                result.aspect_ratio = aspect_ratio;
+            }
+            Box::new(result)
+        }
+        Projection::Orthographic(orthographic) => {
+            let mut result = OrthographicCamera::new(position, orientation, 2.0 * orthographic.xmag());
+            result.aspect_ratio = orthographic.xmag() / orthographic.ymag();
+            Box::new(result)
+        }
+    }
+}
+
+/// Looks at the center of all entities from the front, far enough to see them
+fn get_default_camera(entities: &[Entity]) -> Box<dyn Camera> {
+    let (min, max) = entities.iter()
+        .map(|e| e.aabb())
+        .fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(min, max), aabb| {
+            (min.min(Vec3::new(aabb.min.x, aabb.min.y, aabb.min.z)), max.max(Vec3::new(aabb.max.x, aabb.max.y, aabb.max.z)))
+        });
+    let (center, size) = if entities.is_empty() { (Vec3::ZERO, 1.0) } else { ((min + max) * 0.5, (max - min).length()) };
+    let eye = center - Vec3::new(0.0, size, 0.0);
+    Box::new(PerspectiveCamera::look_at(eye, center, Vec3::new(0.0, 0.0, -1.0), FieldOfView::Vertical(FRAC_PI_2 * 0.5)))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::geometry::ray::Ray;
+    use crate::gltf_import::build_scene;
+
+    /// A red triangle in a translated node and a camera, both facing each other
+    const SCENE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [ { "nodes": [0, 1] } ],
+        "nodes": [
+            { "mesh": 0, "translation": [0, 0, 5] },
+            { "camera": 0, "translation": [0, 0, 10] }
+        ],
+        "cameras": [ { "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1 } } ],
+        "materials": [ { "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0 } } ],
+        "meshes": [ { "primitives": [ { "attributes": { "POSITION": 0 }, "material": 0 } ] } ],
+        "accessors": [ { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0, 0, 0], "max": [1, 1, 0] } ],
+        "bufferViews": [ { "buffer": 0, "byteLength": 36 } ],
+        "buffers": [ { "byteLength": 36, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA" } ]
+    }"#;
+
+    #[test]
+    fn nodes_cameras_and_materials() {
+        let (document, buffers, _) = gltf::import_slice(SCENE.as_bytes()).unwrap();
+        let scene = build_scene(&document, &buffers).unwrap();
+
+        // glTF +y becomes -z and +z becomes +y
+        let ray = Ray::new(Vec3::new(0.2, 0.0, -0.2), Vec3::new(0.0, 1.0, 0.0), 650.0, 1.0);
+        let (entity, hit) = scene.intersect(&ray).unwrap();
+        assert!((hit.position - Vec3::new(0.2, 5.0, -0.2)).length() < 1.0e-5);
+        let material = entity.material.as_ref().unwrap();
+        assert!(material.get_diffuse_reflectance(650.0).unwrap() > 0.9);
+        assert!(material.get_diffuse_reflectance(450.0).unwrap() < 0.1);
+
+        let view = scene.camera.get_ray(0.0, 0.0, 550.0);
+        assert!((view.position - Vec3::new(0.0, 10.0, 0.0)).length() < 1.0e-5);
+        assert!((view.direction - Vec3::new(0.0, -1.0, 0.0)).length() < 1.0e-5);
+    }
+}
@@ -0,0 +1,198 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::path::Path;
+use glam::Vec3;
+
+/// Linear RGB image with floating point pixels, stored row by row from the top
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3>
+}
+
+impl HdrImage {
+
+    /// Reads a Radiance `.hdr` or a `.pfm` file depending on the file extension
+    pub fn read(path: &Path) -> std::io::Result<HdrImage> {
+        let mut reader = BufReader::new(File::open(path)?);
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+            Some("hdr") | Some("pic") => read_radiance_hdr(&mut reader),
+            Some("pfm") => read_pfm(&mut reader),
+            _ => Err(invalid_data("unsupported image format, expected .hdr or .pfm"))
+        }
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y.min(self.height - 1) * self.width + x.min(self.width - 1)]
+    }
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> std::io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(invalid_data("unexpected end of file"));
+    }
+    Ok(line.trim_end_matches(['\n', '\r']).to_string())
+}
+
+fn rgbe_to_rgb(rgbe: [u8; 4]) -> Vec3 {
+    if rgbe[3] == 0 {
+        return Vec3::ZERO;
+    }
+    let f = 2.0_f32.powi(rgbe[3] as i32 - 136);
+    Vec3::new(rgbe[0] as f32 * f, rgbe[1] as f32 * f, rgbe[2] as f32 * f)
+}
+
+pub fn read_radiance_hdr<R: BufRead>(reader: &mut R) -> std::io::Result<HdrImage> {
+    let magic = read_line(reader)?;
+    if !magic.starts_with("#?") {
+        return Err(invalid_data("missing Radiance header"));
+    }
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid_data("only 32-bit_rle_rgbe images are supported"));
+        }
+    }
+    let resolution = read_line(reader)?;
+    let tokens: Vec<&str> = resolution.split_whitespace().collect();
+    if tokens.len() != 4 || tokens[0] != "-Y" || tokens[2] != "+X" {
+        return Err(invalid_data("only -Y +X scanline order is supported"));
+    }
+    let height: usize = tokens[1].parse().map_err(|_| invalid_data("invalid height"))?;
+    let width: usize = tokens[3].parse().map_err(|_| invalid_data("invalid width"))?;
+
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0_u8; 4]; width];
+    for _ in 0..height {
+        let mut start = [0_u8; 4];
+        reader.read_exact(&mut start)?;
+        let run_length_encoded = (8..32768).contains(&width) && start[0] == 2 && start[1] == 2 && start[2] & 0x80 == 0;
+        if !run_length_encoded {
+            scanline[0] = start;
+            for pixel in scanline.iter_mut().skip(1) {
+                reader.read_exact(pixel)?;
+            }
+        } else {
+            if ((start[2] as usize) << 8 | start[3] as usize) != width {
+                return Err(invalid_data("scanline width mismatch"));
+            }
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let mut count = [0_u8; 1];
+                    reader.read_exact(&mut count)?;
+                    let (count, run) = if count[0] > 128 { (count[0] as usize - 128, true) } else { (count[0] as usize, false) };
+                    if count == 0 || x + count > width {
+                        return Err(invalid_data("corrupt run length encoding"));
+                    }
+                    if run {
+                        let mut value = [0_u8; 1];
+                        reader.read_exact(&mut value)?;
+                        for pixel in &mut scanline[x..x + count] {
+                            pixel[channel] = value[0];
+                        }
+                    } else {
+                        let mut values = vec![0_u8; count];
+                        reader.read_exact(&mut values)?;
+                        for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                            pixel[channel] = value;
+                        }
+                    }
+                    x += count;
+                }
+            }
+        }
+        pixels.extend(scanline.iter().map(|rgbe| rgbe_to_rgb(*rgbe)));
+    }
+    Ok(HdrImage { width, height, pixels })
+}
+
+pub fn read_pfm<R: BufRead>(reader: &mut R) -> std::io::Result<HdrImage> {
+    let channels = match read_line(reader)?.trim() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("missing PFM header"))
+    };
+    let size = read_line(reader)?;
+    let mut dimensions = size.split_whitespace().map(|s| s.parse::<usize>());
+    let (width, height) = match (dimensions.next(), dimensions.next()) {
+        (Some(Ok(w)), Some(Ok(h))) => (w, h),
+        _ => return Err(invalid_data("invalid PFM size"))
+    };
+    let scale: f32 = read_line(reader)?.trim().parse().map_err(|_| invalid_data("invalid PFM scale"))?;
+    let little_endian = scale < 0.0;
+
+    let mut data = vec![0_u8; width * height * channels * 4];
+    reader.read_exact(&mut data)?;
+    let values: Vec<f32> = data.chunks_exact(4)
+        .map(|b| {
+            let bytes = [b[0], b[1], b[2], b[3]];
+            if little_endian { f32::from_le_bytes(bytes) } else { f32::from_be_bytes(bytes) }
+        })
+        .collect();
+
+    // PFM stores the bottom row first
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let i = (y * width + x) * channels;
+            pixels.push(if channels == 3 {
+                Vec3::new(values[i], values[i + 1], values[i + 2])
+            } else {
+                Vec3::splat(values[i])
+            });
+        }
+    }
+    Ok(HdrImage { width, height, pixels })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use glam::Vec3;
+    use crate::image::{read_pfm, read_radiance_hdr};
+
+    #[test]
+    fn pfm_little_endian() {
+        let mut data = b"PF\n2 1\n-1.0\n".to_vec();
+        for v in [1.0_f32, 2.0, 3.0, 4.0, 5.0, 6.0] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        let image = read_pfm(&mut Cursor::new(data)).unwrap();
+        assert_eq!((2, 1), (image.width, image.height));
+        assert_eq!(Vec3::new(4.0, 5.0, 6.0), image.get_pixel(1, 0));
+    }
+
+    #[test]
+    fn pfm_rows_are_flipped() {
+        let mut data = b"Pf\n1 2\n1.0\n".to_vec();
+        for v in [1.0_f32, 2.0] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        let image = read_pfm(&mut Cursor::new(data)).unwrap();
+        assert_eq!(Vec3::splat(2.0), image.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn hdr_run_length_encoded() {
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // red: run of 8, green: 8 literal values, blue: run of 8, exponent: run of 8
+        data.extend_from_slice(&[136, 128]);
+        data.extend_from_slice(&[8, 0, 16, 32, 64, 128, 128, 128, 128]);
+        data.extend_from_slice(&[136, 0]);
+        data.extend_from_slice(&[136, 129]);
+        let image = read_radiance_hdr(&mut Cursor::new(data)).unwrap();
+        assert_eq!((8, 1), (image.width, image.height));
+        assert_eq!(Vec3::new(1.0, 0.0, 0.0), image.get_pixel(0, 0));
+        assert_eq!(Vec3::new(1.0, 0.25, 0.0), image.get_pixel(2, 0));
+    }
+}
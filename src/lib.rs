@@ -1,3 +1,4 @@
+pub mod camera;
 pub mod geometry;
 pub mod material;
 pub mod ptrandom;
@@ -5,3 +6,6 @@ pub mod tracer;
 pub mod entity;
 pub mod scene;
 pub mod plotter;
+pub mod image;
+pub mod obj;
+pub mod gltf_import;
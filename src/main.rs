@@ -1,45 +1,41 @@
-mod geometry;
-mod material;
-mod ptrandom;
-mod scene;
-mod entity;
-mod tracer;
-mod plotter;
 
 use std::path::Path;
 use std::fs::File;
 use std::io::BufWriter;
 use std::time::Instant;
-use bvh::aabb::Bounded;
 use ply_rs::{parser, ply};
 use rand::Rng;
 use rayon::iter::ParallelIterator;
 use rayon::prelude::IntoParallelIterator;
 use rtrace::tracer::Photon;
-use crate::entity::Entity;
-use crate::geometry::circle::Circle;
-use crate::geometry::mesh;
-use crate::geometry::mesh::Triangle;
-use glam::{Quat, Vec3, Vec4};
-use crate::material::glossy::GlossyMaterial;
-use crate::scene::{Camera, Scene};
-use crate::geometry::plane::Plane;
-use crate::geometry::sphere::Sphere;
-use crate::geometry::surface::Surface;
-use crate::material::black_body_radiator::BlackBodyRadiator;
-use crate::material::diffuse::{DiffuseGrayMaterial, SimpleDiffuseColoredMaterial};
-use crate::material::glass::{BandPassColoredGlassMaterial, GaussianColoredGlassMaterial, GlassMaterial};
-use crate::material::spectrum_radiator::SpectrumRadiator;
-use crate::plotter::Plotter;
-use crate::tracer::RenderIterator;
+use rtrace::camera::camera::FieldOfView;
+use rtrace::camera::perspective::PerspectiveCamera;
+use rtrace::entity::Entity;
+use rtrace::geometry::circle::Circle;
+use rtrace::geometry::mesh;
+use rtrace::geometry::mesh::Triangle;
+use glam::{Mat4, Quat, Vec3};
+use rtrace::material::glossy::GlossyMaterial;
+use rtrace::scene::Scene;
+use rtrace::geometry::plane::Plane;
+use rtrace::geometry::sphere::Sphere;
+use rtrace::geometry::surface::Surface;
+use rtrace::material::black_body_radiator::BlackBodyRadiator;
+use rtrace::material::diffuse::{DiffuseGrayMaterial, SimpleDiffuseColoredMaterial};
+use rtrace::material::glass::{BandPassColoredGlassMaterial, GaussianColoredGlassMaterial, GlassMaterial};
+use rtrace::material::spectrum_radiator::SpectrumRadiator;
+use rtrace::plotter::Plotter;
+use rtrace::tracer::RenderIterator;
 
 fn main() {
 
-    let scene = create_scene_model();
-
     let width = 512_u16;
     let height = 512_u16;
 
+    let mut scene = create_scene_model();
+    scene.camera.set_aspect_ratio(width as f32 / height as f32);
+    println!("BVH: {}", scene.get_bvh_stats());
+
     let mut plotter = Plotter::new(width, height);
 
     let mut ray_count = 0;
@@ -95,149 +91,128 @@ fn render_scene_parallel(scene: &Scene, width: u16, height: u16, rays_per_pixel:
 }
 
 fn create_scene_color_filter() -> Scene {
-    let light_1 = Entity::LUMINOUS(Box::new(Circle::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 5.0)), Box::new(SpectrumRadiator::new(300.0, 800.0)));
-    let filter_green = Entity::DARK(Box::new(Circle::new(Vec3::new(0.0_f32.to_radians().sin(), 0.1, 0.0_f32.to_radians().cos()), Vec3::new(0.0, 1.0, 0.0), 1.5)), Box::new(BandPassColoredGlassMaterial::new(500.0, 750.0)));
-    let filter_yellow = Entity::DARK(Box::new(Circle::new(Vec3::new(120.0_f32.to_radians().sin(), 0.0, 120.0_f32.to_radians().cos()), Vec3::new(0.0, 1.0, 0.0), 1.5)), Box::new(BandPassColoredGlassMaterial::new(570.0, 585.0)));
-    let filter_red = Entity::DARK(Box::new(Circle::new(Vec3::new(240.0_f32.to_radians().sin(), -0.1, 240.0_f32.to_radians().cos()), Vec3::new(0.0, 1.0, 0.0), 1.5)), Box::new(BandPassColoredGlassMaterial::new(525.0, 540.0)));
+    let light_1 = Entity::luminous(Box::new(Circle::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 5.0)), Box::new(SpectrumRadiator::new(300.0, 800.0)));
+    let filter_green = Entity::dark(Box::new(Circle::new(Vec3::new(0.0_f32.to_radians().sin(), 0.1, 0.0_f32.to_radians().cos()), Vec3::new(0.0, 1.0, 0.0), 1.5)), Box::new(BandPassColoredGlassMaterial::new(500.0, 750.0)));
+    let filter_yellow = Entity::dark(Box::new(Circle::new(Vec3::new(120.0_f32.to_radians().sin(), 0.0, 120.0_f32.to_radians().cos()), Vec3::new(0.0, 1.0, 0.0), 1.5)), Box::new(BandPassColoredGlassMaterial::new(570.0, 585.0)));
+    let filter_red = Entity::dark(Box::new(Circle::new(Vec3::new(240.0_f32.to_radians().sin(), -0.1, 240.0_f32.to_radians().cos()), Vec3::new(0.0, 1.0, 0.0), 1.5)), Box::new(BandPassColoredGlassMaterial::new(525.0, 540.0)));
 
     let entities = vec![light_1, filter_green, filter_yellow, filter_red];
 
-    let camera = Camera::new(
+    let camera = PerspectiveCamera::look_at(
         Vec3::new(0.0, -5.0, 0.0),
-        Quat::from_vec4(Vec4::new(0.0, 1.0, 0.0, 0.0)).normalize(),
-        std::f32::consts::PI * 0.35,
-        4.0,
-        f32::MAX,
-        0.01
-    );
-    Scene::new(entities, camera)
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -1.0),
+        FieldOfView::Horizontal(std::f32::consts::PI * 0.35)
+    ).with_chromatic_aberration(0.01);
+    Scene::new(entities, Box::new(camera))
 }
 
 fn create_scene_simple() -> Scene {
-    let sun1 = Entity::LUMINOUS(Box::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 5.0)), Box::new(BlackBodyRadiator::new(6800.0, 8.0)));
-    let back = Entity::DARK(Box::new(Plane::new(Vec3::new(0.0, 5.5, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(GlossyMaterial::new(0.8, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    let sun1 = Entity::luminous(Box::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 5.0)), Box::new(BlackBodyRadiator::new(6800.0, 8.0)));
+    let back = Entity::dark(Box::new(Plane::new(Vec3::new(0.0, 5.5, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(GlossyMaterial::new(0.8, Box::new(DiffuseGrayMaterial::new(1.0)))));
 
 
     let entities = vec![sun1, back];
-    let camera = Camera::new(
+    let camera = PerspectiveCamera::look_at(
         Vec3::new(0.0, -9.0, 0.0),
-        Quat::from_vec4(Vec4::new(0.0, 1.0, 0.0, 0.0)).normalize(),
-        std::f32::consts::PI * 0.35,
-        4.0,
-        f32::MAX,
-        0.01
-    );
-    Scene::new(entities, camera)
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -1.0),
+        FieldOfView::Horizontal(std::f32::consts::PI * 0.35)
+    ).with_chromatic_aberration(0.01);
+    Scene::new(entities, Box::new(camera))
 }
 
 
 fn create_scene_model() -> Scene {
-    let top = Entity::DARK(Box::new(Plane::new(Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0))), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
-    let bottom = Entity::DARK(Box::new(Plane::new(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 1.0))), Box::new(DiffuseGrayMaterial::new(0.8)));
-    // let front = Entity::DARK(Box::new(Plane::new(Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 500.0, 10.0)));
-    let front = Entity::LUMINOUS(Box::new(Plane::new(Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(BlackBodyRadiator::new(7000.0, 1.0)));
-    let back = Entity::DARK(Box::new(Plane::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(GlossyMaterial::new(0.8, Box::new(DiffuseGrayMaterial::new(1.0)))));
-    let left = Entity::DARK(Box::new(Plane::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 400.0, 20.0)));
-    let right = Entity::DARK(Box::new(Plane::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 600.0, 40.0)));
-
-    // let sun1 = Entity::LUMINOUS(Box::new(Sphere::new(Vec3::new(0.0, 0.0, 9.0), 1.0)), Box::new(BlackBodyRadiator::new(6800.0, 1.0)));
-    // let sun2 = Entity::LUMINOUS(Box::new(Sphere::new(Vec3::new(4.0, 5.0, -3.0), 1.0)), Box::new(BlackBodyRadiator::new(7000.0, 1.5)));
-    let sun3 = Entity::LUMINOUS(Box::new(Sphere::new(Vec3::new(-4.0, 5.0, -5.0), 2.0)), Box::new(BlackBodyRadiator::new(9000.0, 6.0)));
-
-    // let mirror_sphere1 = Entity::DARK(Box::new(Sphere::new(Vec3::new(3.0, 2.0, 7.0), 1.8)), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
-    // let mirror_sphere2 = Entity::DARK(Box::new(Sphere::new(Vec3::new(0.0, 4.0, 8.0), 2.0)), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
-    // let glass_sphere_1 = Entity::DARK(Box::new(Sphere::new(Vec3::new(0.6, -4.0, 4.5), 0.9)), Box::new(GlassMaterial));
-    // let colored_sphere1 = Entity::DARK(Box::new(Sphere::new(Vec3::new(7.0, 5.0, 5.0), 1.99)), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 580.0, 50.0)));
-    // let glass_coating = Entity::DARK(Box::new(Sphere::new(Vec3::new(7.0, 5.0, 5.0), 2.00)), Box::new(GlassMaterial));
-    // let glass_sphere_3 = Entity::DARK(Box::new(Sphere::new(Vec3::new(-4.0, 6.0, 2.0), 3.00)), Box::new(GlassMaterial));
-
-    // let triangle_mirror = Entity::DARK(Box::new(Triangle::new(Vec3::new(-3.0, 9.9, 9.0), Vec3::new(3.0, 9.9, 9.0), Vec3::new(0.0, 9.9, 6.0))), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    let top = Entity::dark(Box::new(Plane::new(Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0))), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    let bottom = Entity::dark(Box::new(Plane::new(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 1.0))), Box::new(DiffuseGrayMaterial::new(0.8)));
+    // let front = Entity::dark(Box::new(Plane::new(Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 500.0, 10.0)));
+    let front = Entity::luminous(Box::new(Plane::new(Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(BlackBodyRadiator::new(7000.0, 1.0)));
+    let back = Entity::dark(Box::new(Plane::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(GlossyMaterial::new(0.8, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    let left = Entity::dark(Box::new(Plane::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 400.0, 20.0)));
+    let right = Entity::dark(Box::new(Plane::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 600.0, 40.0)));
+
+    // let sun1 = Entity::luminous(Box::new(Sphere::new(Vec3::new(0.0, 0.0, 9.0), 1.0)), Box::new(BlackBodyRadiator::new(6800.0, 1.0)));
+    // let sun2 = Entity::luminous(Box::new(Sphere::new(Vec3::new(4.0, 5.0, -3.0), 1.0)), Box::new(BlackBodyRadiator::new(7000.0, 1.5)));
+    let sun3 = Entity::luminous(Box::new(Sphere::new(Vec3::new(-4.0, 5.0, -5.0), 2.0)), Box::new(BlackBodyRadiator::new(9000.0, 6.0)));
+
+    // let mirror_sphere1 = Entity::dark(Box::new(Sphere::new(Vec3::new(3.0, 2.0, 7.0), 1.8)), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    // let mirror_sphere2 = Entity::dark(Box::new(Sphere::new(Vec3::new(0.0, 4.0, 8.0), 2.0)), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    // let glass_sphere_1 = Entity::dark(Box::new(Sphere::new(Vec3::new(0.6, -4.0, 4.5), 0.9)), Box::new(GlassMaterial));
+    // let colored_sphere1 = Entity::dark(Box::new(Sphere::new(Vec3::new(7.0, 5.0, 5.0), 1.99)), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 580.0, 50.0)));
+    // let glass_coating = Entity::dark(Box::new(Sphere::new(Vec3::new(7.0, 5.0, 5.0), 2.00)), Box::new(GlassMaterial));
+    // let glass_sphere_3 = Entity::dark(Box::new(Sphere::new(Vec3::new(-4.0, 6.0, 2.0), 3.00)), Box::new(GlassMaterial));
+
+    // let triangle_mirror = Entity::dark(Box::new(Triangle::new(Vec3::new(-3.0, 9.9, 9.0), Vec3::new(3.0, 9.9, 9.0), Vec3::new(0.0, 9.9, 6.0))), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
 
     // let path = "tetrahedron.ply";
     // let path = "bun_zipper.ply";
     let path = "lucy.ply";
-    let mut f = std::fs::File::open(path).unwrap();
-    let mut bunny_mesh = mesh::read_ply(f);
+    let mut bunny_mesh = mesh::read_ply(Path::new(path)).unwrap_or_else(|e| panic!("Cannot load {}: {}", path, e));
     println!("Loaded mesh");
-    let diameter = (bunny_mesh.aabb().max - bunny_mesh.aabb().min).length();
-    let target_diameter = 12.0;
-    println!("Scale (factor {})", target_diameter / diameter);
-    bunny_mesh = bunny_mesh.scale(target_diameter / diameter);
-    println!("Scaled");
-    bunny_mesh = bunny_mesh.rotate(Quat::from_rotation_x(180.0_f32.to_radians()));
-    println!("Rotated");
-    let center = bunny_mesh.get_center();
-    let t = Vec3::new(-center.x, center.y, 10.0 - bunny_mesh.aabb().max.z);
-    bunny_mesh = bunny_mesh.translate(t);
-    println!("Translated");
-
-    let bunny = Entity::DARK(Box::new(bunny_mesh), Box::new(DiffuseGrayMaterial::new(0.9)));
-    // let bunny = Entity::DARK(Box::new(bunny_mesh), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    let rotation = Mat4::from_rotation_x(180.0_f32.to_radians());
+    let transform = bunny_mesh.get_fit_transform(rotation, 10.0) * rotation;
+    let transform = bunny_mesh.get_floor_transform(transform, Vec3::new(0.0, 0.0, 10.0)) * transform;
+    bunny_mesh = bunny_mesh.transform(transform);
+    println!("Transformed");
+
+    let bunny = Entity::dark(Box::new(bunny_mesh), Box::new(DiffuseGrayMaterial::new(0.9)));
+    // let bunny = Entity::dark(Box::new(bunny_mesh), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
 
     let entities = vec![top, bottom, front, back, left, right, sun3, bunny];
 
-    let camera = Camera::new(
-        Vec3::new(0.0, -8.0, -5.0),
-        Quat::from_vec4(Vec4::new(0.0, 10.0, 1.0, 0.0)).normalize(),
-        std::f32::consts::PI * 0.35,
-        4.0,
-        f32::MAX,
-        0.01
-    );
-    Scene::new(entities, camera)
+    let camera = PerspectiveCamera::look_at(
+        Vec3::new(0.0, -8.83, 3.32),
+        Vec3::new(0.0, 0.97, 5.3),
+        Vec3::new(0.0, 0.0, -1.0),
+        FieldOfView::Horizontal(std::f32::consts::PI * 0.35)
+    ).with_chromatic_aberration(0.01);
+    Scene::new(entities, Box::new(camera))
 }
 
 fn create_scene_box() -> Scene {
-    let top = Entity::DARK(Box::new(Plane::new(Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0))), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
-    let bottom = Entity::DARK(Box::new(Plane::new(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 1.0))), Box::new(DiffuseGrayMaterial::new(0.7)));
-    let front = Entity::DARK(Box::new(Plane::new(Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 500.0, 10.0)));
-    // let front = Entity::LUMINOUS(Box::new(Plane::new(Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(BlackBodyRadiator::new(3000.0, 1.0)));
-    let back = Entity::DARK(Box::new(Plane::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(GlossyMaterial::new(0.8, Box::new(DiffuseGrayMaterial::new(0.7)))));
-    let left = Entity::DARK(Box::new(Plane::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 400.0, 20.0)));
-    let right = Entity::DARK(Box::new(Plane::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 600.0, 40.0)));
-
-    let sun1 = Entity::LUMINOUS(Box::new(Sphere::new(Vec3::new(0.0, 0.0, 9.0), 1.0)), Box::new(BlackBodyRadiator::new(6800.0, 1.0)));
-    let sun2 = Entity::LUMINOUS(Box::new(Sphere::new(Vec3::new(4.0, 5.0, -3.0), 1.0)), Box::new(BlackBodyRadiator::new(7000.0, 1.5)));
-    let sun3 = Entity::LUMINOUS(Box::new(Sphere::new(Vec3::new(-4.0, 5.0, -3.0), 3.0)), Box::new(BlackBodyRadiator::new(9000.0, 1.0)));
-
-    let mirror_sphere1 = Entity::DARK(Box::new(Sphere::new(Vec3::new(3.0, 2.0, 7.0), 1.8)), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
-    let mirror_sphere2 = Entity::DARK(Box::new(Sphere::new(Vec3::new(0.0, 4.0, 8.0), 2.0)), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
-    let glass_sphere_1 = Entity::DARK(Box::new(Sphere::new(Vec3::new(0.6, -4.0, 4.5), 0.9)), Box::new(GlassMaterial));
-    let colored_sphere1 = Entity::DARK(Box::new(Sphere::new(Vec3::new(7.0, 5.0, 5.0), 1.99)), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 580.0, 50.0)));
-    let glass_coating = Entity::DARK(Box::new(Sphere::new(Vec3::new(7.0, 5.0, 5.0), 2.00)), Box::new(GlassMaterial));
-    let glass_sphere_3 = Entity::DARK(Box::new(Sphere::new(Vec3::new(-4.0, 6.0, 2.0), 3.00)), Box::new(GlassMaterial));
-
-    // let triangle_mirror = Entity::DARK(Box::new(Triangle::new(Vec3::new(-3.0, 9.9, 9.0), Vec3::new(3.0, 9.9, 9.0), Vec3::new(0.0, 9.9, 6.0))), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    let top = Entity::dark(Box::new(Plane::new(Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0))), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    let bottom = Entity::dark(Box::new(Plane::new(Vec3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, 1.0))), Box::new(DiffuseGrayMaterial::new(0.7)));
+    let front = Entity::dark(Box::new(Plane::new(Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 500.0, 10.0)));
+    // let front = Entity::luminous(Box::new(Plane::new(Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(BlackBodyRadiator::new(3000.0, 1.0)));
+    let back = Entity::dark(Box::new(Plane::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, 1.0, 0.0))), Box::new(GlossyMaterial::new(0.8, Box::new(DiffuseGrayMaterial::new(0.7)))));
+    let left = Entity::dark(Box::new(Plane::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 400.0, 20.0)));
+    let right = Entity::dark(Box::new(Plane::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 600.0, 40.0)));
+
+    let sun1 = Entity::luminous(Box::new(Sphere::new(Vec3::new(0.0, 0.0, 9.0), 1.0)), Box::new(BlackBodyRadiator::new(6800.0, 1.0)));
+    let sun2 = Entity::luminous(Box::new(Sphere::new(Vec3::new(4.0, 5.0, -3.0), 1.0)), Box::new(BlackBodyRadiator::new(7000.0, 1.5)));
+    let sun3 = Entity::luminous(Box::new(Sphere::new(Vec3::new(-4.0, 5.0, -3.0), 3.0)), Box::new(BlackBodyRadiator::new(9000.0, 1.0)));
+
+    let mirror_sphere1 = Entity::dark(Box::new(Sphere::new(Vec3::new(3.0, 2.0, 7.0), 1.8)), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    let mirror_sphere2 = Entity::dark(Box::new(Sphere::new(Vec3::new(0.0, 4.0, 8.0), 2.0)), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    let glass_sphere_1 = Entity::dark(Box::new(Sphere::new(Vec3::new(0.6, -4.0, 4.5), 0.9)), Box::new(GlassMaterial));
+    let colored_sphere1 = Entity::dark(Box::new(Sphere::new(Vec3::new(7.0, 5.0, 5.0), 1.99)), Box::new(SimpleDiffuseColoredMaterial::new(1.0, 580.0, 50.0)));
+    let glass_coating = Entity::dark(Box::new(Sphere::new(Vec3::new(7.0, 5.0, 5.0), 2.00)), Box::new(GlassMaterial));
+    let glass_sphere_3 = Entity::dark(Box::new(Sphere::new(Vec3::new(-4.0, 6.0, 2.0), 3.00)), Box::new(GlassMaterial));
+
+    // let triangle_mirror = Entity::dark(Box::new(Triangle::new(Vec3::new(-3.0, 9.9, 9.0), Vec3::new(3.0, 9.9, 9.0), Vec3::new(0.0, 9.9, 6.0))), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
 
     // let path = "tetrahedron.ply";
     // let path = "bun_zipper.ply";
     let path = "lucy.ply";
-    let mut f = std::fs::File::open(path).unwrap();
-    let mut bunny_mesh = mesh::read_ply(f);
+    let mut bunny_mesh = mesh::read_ply(Path::new(path)).unwrap_or_else(|e| panic!("Cannot load {}: {}", path, e));
     println!("Loaded mesh");
-    let diameter = (bunny_mesh.aabb().max - bunny_mesh.aabb().min).length();
-    let target_diameter = 6.0;
-    println!("Scale (factor {})", target_diameter / diameter);
-    bunny_mesh = bunny_mesh.scale(target_diameter / diameter);
-    println!("Scaled");
-    bunny_mesh = bunny_mesh.rotate(Quat::from_rotation_x(270.0_f32.to_radians()) * Quat::from_rotation_y(180.0_f32.to_radians()));
-    println!("Rotated");
-    let t = Vec3::new(-4.0, 1.0, 10.0 - bunny_mesh.aabb().max.z);
-    bunny_mesh = bunny_mesh.translate(t);
-    println!("Translated");
-
-    let bunny = Entity::DARK(Box::new(bunny_mesh), Box::new(SimpleDiffuseColoredMaterial::new(0.8, 550.0, 30.0)));
-    // let bunny = Entity::DARK(Box::new(bunny_mesh), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
+    let rotation = Mat4::from_quat(Quat::from_rotation_x(270.0_f32.to_radians()) * Quat::from_rotation_y(180.0_f32.to_radians()));
+    let transform = bunny_mesh.get_fit_transform(rotation, 5.0) * rotation;
+    let transform = bunny_mesh.get_floor_transform(transform, Vec3::new(-4.0, 1.0, 10.0)) * transform;
+    bunny_mesh = bunny_mesh.transform(transform);
+    println!("Transformed");
+
+    let bunny = Entity::dark(Box::new(bunny_mesh), Box::new(SimpleDiffuseColoredMaterial::new(0.8, 550.0, 30.0)));
+    // let bunny = Entity::dark(Box::new(bunny_mesh), Box::new(GlossyMaterial::new(0.0, Box::new(DiffuseGrayMaterial::new(1.0)))));
 
     let entities = vec![top, bottom, front, back, left, right, sun1, sun2, sun3, mirror_sphere1, mirror_sphere2, glass_sphere_1, glass_coating, colored_sphere1, glass_sphere_3, bunny];
 
-    let camera = Camera::new(
-        Vec3::new(0.0, -9.0, -4.0),
-        Quat::from_vec4(Vec4::new(0.0, 10.0, 3.0, 0.0)).normalize(),
-        std::f32::consts::PI * 0.35,
-        4.0,
-        f32::MAX,
-        0.01
-    );
-    Scene::new(entities, camera)
+    let camera = PerspectiveCamera::look_at(
+        Vec3::new(0.0, -9.72, -1.61),
+        Vec3::new(0.0, -1.37, 3.89),
+        Vec3::new(0.0, 0.0, -1.0),
+        FieldOfView::Horizontal(std::f32::consts::PI * 0.35)
+    ).with_chromatic_aberration(0.01);
+    Scene::new(entities, Box::new(camera))
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+use glam::Vec3;
 use crate::material::material::Radiator;
 
 const PLANCKS_CONSTANT: f32 = 6.62606957e-34;
@@ -27,7 +28,7 @@ impl BlackBodyRadiator {
 }
 
 impl Radiator for BlackBodyRadiator {
-    fn get_intensity(&self, wavelength: f32) -> f32 {
+    fn get_intensity(&self, _direction: Vec3, wavelength: f32) -> f32 {
         BlackBodyRadiator::boltzmann_distribution(wavelength, self.temperature) * self.normalization_factor
     }
 }
@@ -1,4 +1,6 @@
+use glam::Vec3;
 use crate::geometry::util;
+use crate::material::rgb_spectrum;
 use super::super::geometry::intersection::Intersection;
 use super::super::geometry::ray::Ray;
 use super::material::Material;
@@ -18,15 +20,15 @@ impl DiffuseGrayMaterial {
 impl Material for DiffuseGrayMaterial {
     fn get_next_ray<'a>(&self, incoming: Ray, intersection: Intersection) -> Ray {
         let hemi = ptrandom::get_hemisphere_vector();
-        let normal = if incoming.direction.dot(intersection.normal) < 0.0 {
-            intersection.normal.clone()
-        } else {
-            intersection.normal * -1.0
-        };
+        let normal = intersection.get_facing_normal();
 
         let direction = util::rotate_towards(hemi, normal);
         Ray::new(intersection.position, direction, incoming.wavelength, self.gray_scale)
     }
+
+    fn get_diffuse_reflectance(&self, _wavelength: f32) -> Option<f32> {
+        Some(self.gray_scale)
+    }
 }
 
 pub struct SimpleDiffuseColoredMaterial {
@@ -39,22 +41,51 @@ impl SimpleDiffuseColoredMaterial {
     pub fn new(brightness: f32, wavelength: f32, deviation: f32) -> SimpleDiffuseColoredMaterial {
         SimpleDiffuseColoredMaterial {wavelength, deviation, brightness}
     }
+
+    fn get_reflectance(&self, wavelength: f32) -> f32 {
+        let p = (self.wavelength - wavelength) / self.deviation;
+        let q = (-0.5 * p * p).exp();
+        self.brightness * q
+    }
 }
 
 impl Material for SimpleDiffuseColoredMaterial {
     fn get_next_ray(&self, incoming: Ray, intersection: Intersection) -> Ray {
         let hemi = ptrandom::get_hemisphere_vector();
-        let normal = if incoming.direction.dot(intersection.normal) < 0.0 {
-            intersection.normal.clone()
-        } else {
-            intersection.normal * -1.0
-        };
+        let normal = intersection.get_facing_normal();
 
         let direction = util::rotate_towards(hemi, normal);
+        let reflectance = self.get_reflectance(incoming.wavelength);
+        Ray::new(intersection.position, direction, incoming.wavelength, reflectance)
+    }
 
-        let p = (self.wavelength - incoming.wavelength) / self.deviation;
-        let q = (-0.5 * p * p).exp();
+    fn get_diffuse_reflectance(&self, wavelength: f32) -> Option<f32> {
+        Some(self.get_reflectance(wavelength))
+    }
+}
+
+/// Lambertian reflector with a linear sRGB albedo, turned into a smooth reflectance spectrum
+pub struct DiffuseRgbMaterial {
+    color: Vec3
+}
 
-        Ray::new(intersection.position, direction, incoming.wavelength, self.brightness * q)
+impl DiffuseRgbMaterial {
+    pub fn new(color: Vec3) -> DiffuseRgbMaterial {
+        DiffuseRgbMaterial { color: color.clamp(Vec3::ZERO, Vec3::ONE) }
     }
-}
\ No newline at end of file
+}
+
+impl Material for DiffuseRgbMaterial {
+    fn get_next_ray(&self, incoming: Ray, intersection: Intersection) -> Ray {
+        let hemi = ptrandom::get_hemisphere_vector();
+        let normal = intersection.get_facing_normal();
+
+        let direction = util::rotate_towards(hemi, normal);
+        let reflectance = rgb_spectrum::rgb_to_spectrum(self.color, incoming.wavelength);
+        Ray::new(intersection.position, direction, incoming.wavelength, reflectance)
+    }
+
+    fn get_diffuse_reflectance(&self, wavelength: f32) -> Option<f32> {
+        Some(rgb_spectrum::rgb_to_spectrum(self.color, wavelength))
+    }
+}
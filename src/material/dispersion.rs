@@ -0,0 +1,82 @@
+/// Wavelength dependent index of refraction, wavelengths are given in nm
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dispersion {
+    Constant(f32),
+    /// Three term Sellmeier equation with the B and C (µm²) coefficients
+    Sellmeier([f32; 3], [f32; 3]),
+    /// Two term Cauchy equation with the A and B (µm²) coefficients
+    Cauchy(f32, f32)
+}
+
+pub const SF11: Dispersion = Dispersion::Sellmeier([1.737597, 0.31374735, 1.8987811], [0.013188707, 0.062306814, 155.2363]);
+pub const BK7: Dispersion = Dispersion::Sellmeier([1.0396122, 0.23179235, 1.0104694], [0.0060006985, 0.020017914, 103.56065]);
+pub const F2: Dispersion = Dispersion::Sellmeier([1.3453336, 0.20907317, 0.9373572], [0.0099774385, 0.047045078, 111.886765]);
+pub const BAF10: Dispersion = Dispersion::Sellmeier([1.5851495, 0.14355938, 1.0852127], [0.009266813, 0.04244898, 105.61357]);
+pub const SK16: Dispersion = Dispersion::Sellmeier([1.3431778, 0.2411444, 0.99431795], [0.007046873, 0.0229005, 92.750854]);
+pub const LAK9: Dispersion = Dispersion::Sellmeier([1.462319, 0.3443996, 1.1550838], [0.0072427015, 0.024335314, 85.46869]);
+pub const SF5: Dispersion = Dispersion::Sellmeier([1.5248189, 0.18708552, 1.4272902], [0.011254756, 0.05889954, 129.14168]);
+pub const WATER: Dispersion = Dispersion::Cauchy(1.3247, 0.0033);
+pub const FUSED_SILICA: Dispersion = Dispersion::Sellmeier([0.6961663, 0.4079426, 0.8974794], [0.004679148, 0.013512063, 97.934006]);
+
+impl Dispersion {
+
+    pub fn get_refraction_index(&self, wavelength: f32) -> f32 {
+        match self {
+            Dispersion::Constant(ior) => *ior,
+            Dispersion::Sellmeier(b, c) => {
+                let w2 = wavelength * wavelength * 1.0e-6;
+                (1.0
+                    + b[0] * w2 / (w2 - c[0])
+                    + b[1] * w2 / (w2 - c[1])
+                    + b[2] * w2 / (w2 - c[2])
+                ).sqrt()
+            }
+            Dispersion::Cauchy(a, b) => a + b / (wavelength * wavelength * 1.0e-6)
+        }
+    }
+
+    /// Looks up a glass by its catalog name, or parses a constant index of refraction
+    pub fn from_name(name: &str) -> Option<Dispersion> {
+        match name.to_uppercase().trim_start_matches("N-") {
+            "AIR" | "VACUUM" => Some(Dispersion::Constant(1.0)),
+            "SF11" => Some(SF11),
+            "BK7" => Some(BK7),
+            "F2" => Some(F2),
+            "BAF10" => Some(BAF10),
+            "SK16" => Some(SK16),
+            "LAK9" => Some(LAK9),
+            "SF5" => Some(SF5),
+            "FUSED_SILICA" | "SIO2" => Some(FUSED_SILICA),
+            "WATER" | "H2O" => Some(WATER),
+            other => other.parse::<f32>().ok().map(Dispersion::Constant)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::material::dispersion::{BK7, Dispersion, SF11, WATER};
+
+    #[test]
+    fn bk7_d_line() {
+        assert!((BK7.get_refraction_index(587.6) - 1.5168).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn sf11_is_dispersive() {
+        assert!(SF11.get_refraction_index(400.0) > SF11.get_refraction_index(700.0));
+    }
+
+    #[test]
+    fn water_d_line() {
+        assert!((WATER.get_refraction_index(589.3) - 1.333).abs() < 2.0e-3);
+    }
+
+    #[test]
+    fn from_name() {
+        assert_eq!(Dispersion::from_name("N-BK7"), Some(BK7));
+        assert_eq!(Dispersion::from_name("air"), Some(Dispersion::Constant(1.0)));
+        assert_eq!(Dispersion::from_name("1.5"), Some(Dispersion::Constant(1.5)));
+        assert_eq!(Dispersion::from_name("unobtainium"), None);
+    }
+}
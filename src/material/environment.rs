@@ -0,0 +1,212 @@
+use std::f32::consts::PI;
+use glam::{const_vec3, Quat, Vec3};
+use crate::image::HdrImage;
+use crate::material::material::Radiator;
+use crate::material::rgb_spectrum;
+use crate::ptrandom;
+
+const UNIFORM_PDF: f32 = 1.0 / (4.0 * PI);
+
+pub const ZENITH: Vec3 = const_vec3!([0.0, 0.0, -1.0]);
+
+/// Light arriving from infinitely far away, seen by every ray that leaves the scene.
+/// Environments have their zenith at -z, matching the floors of the scenes.
+pub trait Environment: Sync + Send {
+    /// Radiance arriving from `direction`
+    fn get_intensity(&self, direction: Vec3, wavelength: f32) -> f32;
+
+    /// Samples a direction pointing into the environment, returned with its probability density
+    /// with respect to solid angle
+    fn sample_direction(&self) -> (Vec3, f32) {
+        (ptrandom::get_sphere_vector(), UNIFORM_PDF)
+    }
+
+    fn get_pdf(&self, _direction: Vec3) -> f32 {
+        UNIFORM_PDF
+    }
+}
+
+/// Uniform environment with the spectrum of a radiator, e.g. a constant or a black body spectrum
+pub struct RadiatorEnvironment {
+    radiator: Box<dyn Radiator>
+}
+
+impl RadiatorEnvironment {
+    pub fn new(radiator: Box<dyn Radiator>) -> RadiatorEnvironment {
+        RadiatorEnvironment { radiator }
+    }
+}
+
+impl Environment for RadiatorEnvironment {
+    fn get_intensity(&self, direction: Vec3, wavelength: f32) -> f32 {
+        self.radiator.get_intensity(-direction, wavelength)
+    }
+}
+
+/// Piecewise constant distribution over the unit square, used to importance sample images
+struct Distribution2D {
+    width: usize,
+    height: usize,
+    weights: Vec<f32>,
+    /// Cumulative distribution of each row, `width + 1` entries per row
+    conditional: Vec<f32>,
+    /// Cumulative distribution over the rows
+    marginal: Vec<f32>,
+    total: f32
+}
+
+impl Distribution2D {
+    fn new(width: usize, height: usize, mut weights: Vec<f32>) -> Distribution2D {
+        let mut total: f32 = weights.iter().sum();
+        if total <= 0.0 || !total.is_finite() {
+            weights = vec![1.0; width * height];
+            total = (width * height) as f32;
+        }
+        let mut conditional = Vec::with_capacity((width + 1) * height);
+        let mut marginal = Vec::with_capacity(height + 1);
+        marginal.push(0.0);
+        for row in weights.chunks(width) {
+            let row_total: f32 = row.iter().sum();
+            let mut sum = 0.0;
+            conditional.push(0.0);
+            for w in row {
+                sum += w;
+                conditional.push(if row_total > 0.0 { sum / row_total } else { 0.0 });
+            }
+            marginal.push(marginal.last().unwrap() + row_total / total);
+        }
+        Distribution2D { width, height, weights, conditional, marginal, total }
+    }
+
+    /// Finds the bin of `u` in a cumulative distribution and the relative offset inside of it
+    fn sample_cdf(cdf: &[f32], u: f32) -> (usize, f32) {
+        let bins = cdf.len() - 1;
+        let index = (cdf.partition_point(|c| *c <= u).max(1) - 1).min(bins - 1);
+        let width = cdf[index + 1] - cdf[index];
+        let offset = if width > 0.0 { ((u - cdf[index]) / width).min(1.0) } else { 0.5 };
+        (index, offset)
+    }
+
+    /// Returns continuous coordinates in the unit square and their probability density
+    fn sample(&self, u1: f32, u2: f32) -> (f32, f32, f32) {
+        let (y, dy) = Distribution2D::sample_cdf(&self.marginal, u1);
+        let row = &self.conditional[y * (self.width + 1)..(y + 1) * (self.width + 1)];
+        let (x, dx) = Distribution2D::sample_cdf(row, u2);
+        ((x as f32 + dx) / self.width as f32, (y as f32 + dy) / self.height as f32, self.get_pdf(x, y))
+    }
+
+    fn get_pdf(&self, x: usize, y: usize) -> f32 {
+        self.weights[y * self.width + x] * (self.width * self.height) as f32 / self.total
+    }
+}
+
+/// Equirectangular HDR image, the top row of the image is the zenith
+pub struct ImageEnvironment {
+    image: HdrImage,
+    rotation: Quat,
+    intensity: f32,
+    distribution: Distribution2D
+}
+
+impl ImageEnvironment {
+    pub fn new(image: HdrImage, rotation: Quat, intensity: f32) -> ImageEnvironment {
+        let weights = image.pixels.iter()
+            .enumerate()
+            .map(|(i, rgb)| {
+                let theta = ((i / image.width) as f32 + 0.5) / image.height as f32 * PI;
+                rgb_spectrum::get_luminance(*rgb).max(0.0) * theta.sin()
+            })
+            .collect();
+        let distribution = Distribution2D::new(image.width, image.height, weights);
+        ImageEnvironment { image, rotation, intensity, distribution }
+    }
+
+    /// Maps a direction to coordinates in the unit square and the sine of its angle to the zenith
+    fn get_uv(&self, direction: Vec3) -> (f32, f32, f32) {
+        let local = self.rotation.inverse().mul_vec3(direction).normalize();
+        let theta = local.dot(ZENITH).clamp(-1.0, 1.0).acos();
+        let phi = local.y.atan2(local.x);
+        ((phi + PI) / (2.0 * PI), theta / PI, theta.sin())
+    }
+
+    fn get_pixel_index(&self, u: f32, v: f32) -> (usize, usize) {
+        (((u * self.image.width as f32) as usize).min(self.image.width - 1),
+         ((v * self.image.height as f32) as usize).min(self.image.height - 1))
+    }
+}
+
+impl Environment for ImageEnvironment {
+    fn get_intensity(&self, direction: Vec3, wavelength: f32) -> f32 {
+        let (u, v, _) = self.get_uv(direction);
+        let (x, y) = self.get_pixel_index(u, v);
+        rgb_spectrum::rgb_to_spectrum(self.image.get_pixel(x, y), wavelength) * self.intensity
+    }
+
+    fn sample_direction(&self) -> (Vec3, f32) {
+        let (u, v, pdf) = self.distribution.sample(ptrandom::get_unit(), ptrandom::get_unit());
+        let phi = u * 2.0 * PI - PI;
+        let theta = v * PI;
+        let local = Vec3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), -theta.cos());
+        let sin_theta = theta.sin();
+        let pdf = if sin_theta > 0.0 { pdf / (2.0 * PI * PI * sin_theta) } else { 0.0 };
+        (self.rotation.mul_vec3(local), pdf)
+    }
+
+    fn get_pdf(&self, direction: Vec3) -> f32 {
+        let (u, v, sin_theta) = self.get_uv(direction);
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let (x, y) = self.get_pixel_index(u, v);
+        self.distribution.get_pdf(x, y) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+    use crate::image::HdrImage;
+    use crate::material::environment::{Environment, ImageEnvironment};
+
+    fn single_bright_pixel() -> ImageEnvironment {
+        let mut pixels = vec![Vec3::splat(0.01); 8 * 4];
+        pixels[8 + 3] = Vec3::splat(100.0);
+        ImageEnvironment::new(HdrImage { width: 8, height: 4, pixels }, Quat::IDENTITY, 1.0)
+    }
+
+    #[test]
+    fn samples_bright_pixel() {
+        let environment = single_bright_pixel();
+        let bright = (0..1000)
+            .map(|_| environment.sample_direction())
+            .filter(|(d, _)| environment.get_intensity(*d, 550.0) > 1.0)
+            .count();
+        assert!(bright > 900);
+    }
+
+    #[test]
+    fn sampled_pdf_matches_lookup() {
+        let environment = single_bright_pixel();
+        for _ in 0..100 {
+            let (direction, pdf) = environment.sample_direction();
+            assert!((environment.get_pdf(direction) - pdf).abs() <= pdf * 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let environment = single_bright_pixel();
+        let n = 200;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let theta = (i as f32 + 0.5) / n as f32 * std::f32::consts::PI;
+                let phi = (j as f32 + 0.5) / n as f32 * 2.0 * std::f32::consts::PI;
+                let direction = Vec3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos());
+                sum += environment.get_pdf(direction) * theta.sin();
+            }
+        }
+        let integral = sum * std::f32::consts::PI / n as f32 * 2.0 * std::f32::consts::PI / n as f32;
+        assert!((integral - 1.0).abs() < 0.05, "integral {}", integral);
+    }
+}
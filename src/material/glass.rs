@@ -2,6 +2,8 @@ use crate::geometry::intersection::Intersection;
 use crate::geometry::ray::Ray;
 use glam::Vec3;
 use crate::geometry::util;
+use crate::material::dispersion;
+use crate::material::dispersion::Dispersion;
 use crate::material::material::Material;
 use crate::ptrandom;
 
@@ -31,15 +33,6 @@ fn get_fresnel(vec_in: &Vec3, vec_normal: Vec3, ior: f32) -> f32 {
     (rs * rs + rp * rp) / 2.0
 }
 
-fn get_refraction_index(wavelength: f32) -> f32 {
-    let w2 =  wavelength * wavelength * 1.0e-6;
-    (1.0
-        + 1.737596950 * w2 / (w2 - 0.0131887070)
-        + 0.313747346 * w2 / (w2 - 0.0623068142)
-        + 1.898781010 * w2 / (w2 - 155.23629000)
-    ).sqrt()
-}
-
 fn clamp(v: f32) -> f32 {
     return if v < -1.0 {
         -1.0
@@ -50,23 +43,21 @@ fn clamp(v: f32) -> f32 {
     }
 }
 
-fn get_next_ray(incoming: Ray, intersection: Intersection) -> Ray {
-    let mut ior = get_refraction_index(incoming.wavelength);
+/// Reflects or refracts at the interface between a dielectric with index of refraction `ior` and
+/// the medium outside of it
+fn get_next_ray(incoming: Ray, intersection: Intersection, ior: f32) -> Ray {
+    let mut ior = ior / intersection.exterior_ior;
     let fresnel = get_fresnel(&incoming.direction, intersection.normal, ior);
     let path = ptrandom::get_unit();
 
     let direction = if path < fresnel {
         util::reflect(incoming.direction, intersection.normal)
     } else {
-        let mut cosi = -incoming.direction.dot(intersection.normal);
-        let mut normal = intersection.normal;
-        if cosi > 0.0 {
+        let normal = intersection.get_facing_normal();
+        let cosi = -incoming.direction.dot(normal).min(0.0);
+        if intersection.front_face {
             ior = 1.0 / ior;
         }
-        else {
-            normal = normal * -1.0;
-            cosi = -cosi;
-        }
         let sin_tsqr = ior * ior * (1.0 - cosi * cosi);
         if sin_tsqr > 1.0 {
             util::reflect(incoming.direction, normal)
@@ -83,7 +74,44 @@ impl GlassMaterial {
 
 impl Material for GlassMaterial {
     fn get_next_ray(&self, incoming: Ray, intersection: Intersection) -> Ray {
-        super::glass::get_next_ray(incoming, intersection)
+        let ior = dispersion::SF11.get_refraction_index(incoming.wavelength);
+        super::glass::get_next_ray(incoming, intersection, ior)
+    }
+
+    fn get_refraction_index(&self, wavelength: f32) -> Option<f32> {
+        Some(dispersion::SF11.get_refraction_index(wavelength))
+    }
+}
+
+/// Clear dielectric, e.g. a glass or a liquid, with any dispersion
+pub struct DielectricMaterial {
+    dispersion: Dispersion,
+    priority: i32
+}
+
+impl DielectricMaterial {
+    pub fn new(dispersion: Dispersion) -> DielectricMaterial {
+        DielectricMaterial { dispersion, priority: 0 }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> DielectricMaterial {
+        self.priority = priority;
+        self
+    }
+}
+
+impl Material for DielectricMaterial {
+    fn get_next_ray(&self, incoming: Ray, intersection: Intersection) -> Ray {
+        let ior = self.dispersion.get_refraction_index(incoming.wavelength);
+        super::glass::get_next_ray(incoming, intersection, ior)
+    }
+
+    fn get_refraction_index(&self, wavelength: f32) -> Option<f32> {
+        Some(self.dispersion.get_refraction_index(wavelength))
+    }
+
+    fn get_priority(&self) -> i32 {
+        self.priority
     }
 }
 
@@ -102,10 +130,15 @@ impl Material for GaussianColoredGlassMaterial {
     fn get_next_ray(&self, incoming: Ray, intersection: Intersection) -> Ray {
         let p = (self.wavelength - incoming.wavelength) / self.deviation;
         let q = (-0.5 * p * p).exp();
-        let mut ray = super::glass::get_next_ray(incoming, intersection);
+        let ior = dispersion::SF11.get_refraction_index(incoming.wavelength);
+        let mut ray = super::glass::get_next_ray(incoming, intersection, ior);
         ray.strength = ray.strength * q;
         ray
     }
+
+    fn get_refraction_index(&self, wavelength: f32) -> Option<f32> {
+        Some(dispersion::SF11.get_refraction_index(wavelength))
+    }
 }
 
 pub struct BandPassColoredGlassMaterial {
@@ -126,8 +159,33 @@ impl Material for BandPassColoredGlassMaterial {
         } else {
             0.0
         };
-        let mut ray = super::glass::get_next_ray(incoming, intersection);
+        let ior = dispersion::SF11.get_refraction_index(incoming.wavelength);
+        let mut ray = super::glass::get_next_ray(incoming, intersection, ior);
         ray.strength = strength;
         ray
     }
-}
\ No newline at end of file
+
+    fn get_refraction_index(&self, wavelength: f32) -> Option<f32> {
+        Some(dispersion::SF11.get_refraction_index(wavelength))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::geometry::intersection::Intersection;
+    use crate::geometry::ray::Ray;
+    use crate::material::dispersion::WATER;
+    use crate::material::glass::DielectricMaterial;
+    use crate::material::material::Material;
+
+    #[test]
+    fn matching_exterior_is_invisible() {
+        let water = DielectricMaterial::new(WATER);
+        let direction = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let incoming = Ray::new(Vec3::new(-1.0, -1.0, 0.0), direction, 550.0, 1.0);
+        let mut intersection = Intersection::new(&incoming, 2.0_f32.sqrt(), Vec3::new(0.0, -1.0, 0.0), Vec3::X);
+        intersection.exterior_ior = WATER.get_refraction_index(550.0);
+        let ray = water.get_next_ray(incoming, intersection);
+        assert!((ray.direction - direction).length() < 1.0e-5);
+    }
+}
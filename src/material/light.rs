@@ -0,0 +1,157 @@
+use glam::Vec3;
+use crate::material::material::Radiator;
+
+/// Light arriving at a point from a light source without any area
+pub struct LightSample {
+    /// Direction from the lit point towards the light
+    pub direction: Vec3,
+    pub distance: f32,
+    /// Irradiance at the point, perpendicular to `direction`
+    pub intensity: f32
+}
+
+/// Light sources that are infinitely small, which paths never hit by chance. The tracer samples
+/// them explicitly from diffuse surfaces and casts a shadow ray towards them.
+pub trait Light: Sync + Send {
+    fn sample(&self, position: Vec3, wavelength: f32) -> Option<LightSample>;
+}
+
+/// Emits equally in all directions, the radiator spectrum is the radiant intensity per steradian
+pub struct PointLight {
+    position: Vec3,
+    radiator: Box<dyn Radiator>
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, radiator: Box<dyn Radiator>) -> PointLight {
+        PointLight { position, radiator }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, position: Vec3, wavelength: f32) -> Option<LightSample> {
+        let offset = self.position - position;
+        let distance_squared = offset.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        Some(LightSample {
+            direction: offset / distance,
+            distance,
+            intensity: self.radiator.get_intensity(-offset / distance, wavelength) / distance_squared
+        })
+    }
+}
+
+/// Point light restricted to a cone around `direction`. The intensity is constant up to
+/// `falloff_angle` and fades smoothly to zero at `cone_angle`, both measured from the axis.
+pub struct SpotLight {
+    position: Vec3,
+    direction: Vec3,
+    cos_cone: f32,
+    cos_falloff: f32,
+    radiator: Box<dyn Radiator>
+}
+
+impl SpotLight {
+    pub fn new(position: Vec3, direction: Vec3, cone_angle: f32, falloff_angle: f32, radiator: Box<dyn Radiator>) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            cos_cone: cone_angle.cos(),
+            cos_falloff: falloff_angle.min(cone_angle).cos(),
+            radiator
+        }
+    }
+
+    fn get_falloff(&self, cos: f32) -> f32 {
+        if cos < self.cos_cone {
+            0.0
+        } else if cos >= self.cos_falloff {
+            1.0
+        } else {
+            let t = (cos - self.cos_cone) / (self.cos_falloff - self.cos_cone);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, position: Vec3, wavelength: f32) -> Option<LightSample> {
+        let offset = self.position - position;
+        let distance_squared = offset.length_squared();
+        if distance_squared <= 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = offset / distance;
+        let falloff = self.get_falloff(-direction.dot(self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            intensity: self.radiator.get_intensity(-direction, wavelength) * falloff / distance_squared
+        })
+    }
+}
+
+/// Parallel light from infinitely far away, like the sun. `direction` is the direction the light
+/// travels in and the radiator spectrum is the irradiance perpendicular to it.
+pub struct DirectionalLight {
+    direction: Vec3,
+    radiator: Box<dyn Radiator>
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, radiator: Box<dyn Radiator>) -> DirectionalLight {
+        DirectionalLight { direction: direction.normalize(), radiator }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _position: Vec3, wavelength: f32) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: f32::INFINITY,
+            intensity: self.radiator.get_intensity(self.direction, wavelength)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::material::light::{DirectionalLight, Light, PointLight, SpotLight};
+    use crate::material::spectrum_radiator::SpectrumRadiator;
+
+    #[test]
+    fn point_light_inverse_square() {
+        let light = PointLight::new(Vec3::new(0.0, 0.0, -2.0), Box::new(SpectrumRadiator::new(300.0, 800.0)));
+        let near = light.sample(Vec3::new(0.0, 0.0, -1.0), 550.0).unwrap();
+        let far = light.sample(Vec3::new(0.0, 0.0, 0.0), 550.0).unwrap();
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), far.direction);
+        assert_eq!(2.0, far.distance);
+        assert!((near.intensity - 4.0 * far.intensity).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn spot_light_cone() {
+        let light = SpotLight::new(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0), 0.5, 0.25, Box::new(SpectrumRadiator::new(300.0, 800.0)));
+        let center = light.sample(Vec3::new(0.0, 0.0, 1.0), 550.0).unwrap().intensity;
+        let edge = light.sample(Vec3::new(0.4f32.tan(), 0.0, 1.0), 550.0).unwrap().intensity;
+        assert!(edge > 0.0 && edge < center);
+        assert!(light.sample(Vec3::new(1.0, 0.0, 1.0), 550.0).is_none());
+        assert!(light.sample(Vec3::new(0.0, 0.0, -1.0), 550.0).is_none());
+    }
+
+    #[test]
+    fn directional_light_points_back() {
+        let light = DirectionalLight::new(Vec3::new(0.0, 0.0, 2.0), Box::new(SpectrumRadiator::new(300.0, 800.0)));
+        let sample = light.sample(Vec3::new(5.0, 3.0, 1.0), 550.0).unwrap();
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), sample.direction);
+        assert!(sample.distance.is_infinite());
+    }
+}
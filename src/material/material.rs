@@ -1,10 +1,29 @@
+use glam::Vec3;
 use super::super::geometry::intersection::Intersection;
 use super::super::geometry::ray::Ray;
 
 pub trait Material: Sync + Send {
     fn get_next_ray(&self, incoming: Ray, intersection: Intersection) -> Ray;
+
+    /// Albedo of materials that scatter like a Lambertian surface. The tracer samples light
+    /// sources explicitly for those.
+    fn get_diffuse_reflectance(&self, _wavelength: f32) -> Option<f32> {
+        None
+    }
+
+    /// Index of refraction of dielectrics that enclose a medium, which the tracer keeps track of
+    /// when rays are refracted into them
+    fn get_refraction_index(&self, _wavelength: f32) -> Option<f32> {
+        None
+    }
+
+    /// Where dielectrics overlap, the one with the higher priority fills the overlapping volume
+    fn get_priority(&self) -> i32 {
+        0
+    }
 }
 
 pub trait Radiator: Sync + Send {
-    fn get_intensity(&self, wavelength: f32) -> f32;
-}
\ No newline at end of file
+    /// Emitted intensity at `wavelength` for light leaving the radiator in `direction`
+    fn get_intensity(&self, direction: Vec3, wavelength: f32) -> f32;
+}
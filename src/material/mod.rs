@@ -3,4 +3,12 @@ pub mod glossy;
 pub mod diffuse;
 pub mod black_body_radiator;
 pub mod glass;
-pub mod spectrum_radiator;
\ No newline at end of file
+pub mod spectrum_radiator;
+pub mod dispersion;
+pub mod rgb_spectrum;
+pub mod cie;
+pub mod environment;
+pub mod sky;
+pub mod light;
+pub mod photometry;
+pub mod tabulated_radiator;
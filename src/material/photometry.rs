@@ -0,0 +1,324 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use glam::{Quat, Vec3};
+use crate::material::material::Radiator;
+
+/// Symmetry of a goniometric table, which only stores the planes that are not mirror images
+pub enum Symmetry {
+    /// All planes from 0 to 360°
+    Full,
+    /// A single plane, rotationally symmetric around the vertical axis
+    Rotational,
+    /// Mirror symmetric about the plane at the given angle, e.g. the C0-C180 plane
+    Bilateral(f32),
+    /// Mirror symmetric about both the C0-C180 and the C90-C270 plane
+    Quadrant
+}
+
+/// Luminous intensity distribution of a luminaire in type C photometry. Vertical angles are
+/// measured from the nadir, horizontal angles around it. In the local frame of the luminaire the
+/// nadir is +z and the C0 plane contains +x, the C90 plane +y.
+pub struct PhotometricProfile {
+    /// Vertical angles in radians
+    vertical_angles: Vec<f32>,
+    /// Horizontal angles in radians, ascending
+    horizontal_angles: Vec<f32>,
+    /// Candela for each horizontal angle, for each vertical angle
+    candela: Vec<Vec<f32>>,
+    symmetry: Symmetry
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+impl PhotometricProfile {
+    pub fn new(vertical_angles: Vec<f32>, mut horizontal_angles: Vec<f32>, mut candela: Vec<Vec<f32>>, symmetry: Symmetry) -> PhotometricProfile {
+        // Close the circle so that interpolation wraps around between the last and the first plane
+        if let Symmetry::Full = symmetry {
+            let first = horizontal_angles[0];
+            if *horizontal_angles.last().unwrap() < first + 2.0 * PI - 1.0e-4 {
+                horizontal_angles.push(first + 2.0 * PI);
+                candela.push(candela[0].clone());
+            }
+        }
+        PhotometricProfile { vertical_angles, horizontal_angles, candela, symmetry }
+    }
+
+    /// Reads an IES LM-63 `.ies` or an EULUMDAT `.ldt` file depending on the file extension
+    pub fn read(path: &Path) -> std::io::Result<PhotometricProfile> {
+        let bytes = fs::read(path)?;
+        // Both formats are commonly written in Latin-1
+        let text: String = bytes.iter().map(|b| *b as char).collect();
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+            Some("ies") => parse_ies(&text),
+            Some("ldt") => parse_eulumdat(&text),
+            _ => Err(invalid_data("unsupported photometric format, expected .ies or .ldt"))
+        }
+    }
+
+    /// Luminous intensity in candela towards `direction`, given in the frame of the luminaire
+    pub fn get_candela(&self, direction: Vec3) -> f32 {
+        let direction = direction.normalize();
+        let vertical = direction.z.clamp(-1.0, 1.0).acos();
+        let first = self.vertical_angles[0];
+        let last = *self.vertical_angles.last().unwrap();
+        if vertical < first - 1.0e-4 || vertical > last + 1.0e-4 {
+            return 0.0;
+        }
+        let (v, dv) = find_interval(&self.vertical_angles, vertical);
+        if self.horizontal_angles.len() == 1 {
+            return lerp(&self.candela[0], v, dv);
+        }
+        let horizontal = self.fold_horizontal(direction.y.atan2(direction.x));
+        let (h, dh) = find_interval(&self.horizontal_angles, horizontal);
+        let c1 = lerp(&self.candela[h], v, dv);
+        let c2 = if h + 1 < self.candela.len() { lerp(&self.candela[h + 1], v, dv) } else { c1 };
+        c1 * (1.0 - dh) + c2 * dh
+    }
+
+    /// Maps a horizontal angle onto the stored planes using the symmetry of the profile
+    fn fold_horizontal(&self, angle: f32) -> f32 {
+        let candidates = match self.symmetry {
+            Symmetry::Full | Symmetry::Rotational => vec![angle],
+            Symmetry::Bilateral(axis) => vec![angle, 2.0 * axis - angle],
+            Symmetry::Quadrant => vec![angle, -angle, PI - angle, PI + angle]
+        };
+        let first = self.horizontal_angles[0];
+        let last = *self.horizontal_angles.last().unwrap();
+        let normalize = |a: f32| first + (a - first).rem_euclid(2.0 * PI);
+        candidates.iter()
+            .map(|a| normalize(*a))
+            .find(|a| *a <= last + 1.0e-4)
+            .unwrap_or_else(|| normalize(angle))
+    }
+}
+
+/// Finds the interval of an ascending list of angles that contains `angle`, clamped to its ends
+fn find_interval(angles: &[f32], angle: f32) -> (usize, f32) {
+    if angles.len() == 1 || angle <= angles[0] {
+        return (0, 0.0);
+    }
+    let index = (angles.partition_point(|a| *a <= angle).max(1) - 1).min(angles.len() - 2);
+    let width = angles[index + 1] - angles[index];
+    let offset = if width > 0.0 { ((angle - angles[index]) / width).clamp(0.0, 1.0) } else { 0.0 };
+    (index, offset)
+}
+
+fn lerp(values: &[f32], index: usize, offset: f32) -> f32 {
+    if index + 1 < values.len() {
+        values[index] * (1.0 - offset) + values[index + 1] * offset
+    } else {
+        values[index]
+    }
+}
+
+/// Parses an IES LM-63 file with type C photometry
+pub fn parse_ies(text: &str) -> std::io::Result<PhotometricProfile> {
+    let mut lines = text.lines();
+    let tilt = loop {
+        match lines.next() {
+            Some(line) if line.trim_start().starts_with("TILT=") => break line.trim_start()[5..].trim().to_string(),
+            Some(_) => continue,
+            None => return Err(invalid_data("missing TILT line"))
+        }
+    };
+    let mut values = lines
+        .flat_map(|l| l.split(|c: char| c.is_whitespace() || c == ','))
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f32>().map_err(|_| invalid_data("invalid number")));
+    let mut next = || values.next().unwrap_or_else(|| Err(invalid_data("unexpected end of file")));
+
+    if tilt == "INCLUDE" {
+        // Lamp to luminaire geometry, followed by pairs of angles and multipliers
+        next()?;
+        let pairs = next()? as usize;
+        for _ in 0..pairs * 2 {
+            next()?;
+        }
+    }
+    let _lamps = next()?;
+    let _lumens_per_lamp = next()?;
+    let multiplier = next()?;
+    let vertical_count = next()? as usize;
+    let horizontal_count = next()? as usize;
+    let photometric_type = next()?;
+    if photometric_type != 1.0 {
+        return Err(invalid_data("only type C photometry is supported"));
+    }
+    let _units = next()?;
+    for _ in 0..3 {
+        next()?;
+    }
+    let ballast_factor = next()?;
+    let _ballast_lamp_factor = next()?;
+    let _input_watts = next()?;
+    if vertical_count == 0 || horizontal_count == 0 {
+        return Err(invalid_data("empty candela table"));
+    }
+
+    let vertical_angles = (0..vertical_count).map(|_| next().map(f32::to_radians)).collect::<Result<Vec<f32>, Error>>()?;
+    let horizontal_angles = (0..horizontal_count).map(|_| next().map(f32::to_radians)).collect::<Result<Vec<f32>, Error>>()?;
+    let mut candela = Vec::with_capacity(horizontal_count);
+    for _ in 0..horizontal_count {
+        candela.push((0..vertical_count).map(|_| next().map(|c| c * multiplier * ballast_factor)).collect::<Result<Vec<f32>, Error>>()?);
+    }
+
+    let first = horizontal_angles[0].to_degrees().round();
+    let last = horizontal_angles.last().unwrap().to_degrees().round();
+    let symmetry = match (first as i32, last as i32) {
+        (0, 0) => Symmetry::Rotational,
+        (0, 90) => Symmetry::Quadrant,
+        (0, 180) => Symmetry::Bilateral(0.0),
+        (90, 270) => Symmetry::Bilateral(PI * 0.5),
+        _ => Symmetry::Full
+    };
+    Ok(PhotometricProfile::new(vertical_angles, horizontal_angles, candela, symmetry))
+}
+
+/// Parses an EULUMDAT file, intensities are converted from cd/klm to candela using the luminous
+/// flux of the first lamp set
+pub fn parse_eulumdat(text: &str) -> std::io::Result<PhotometricProfile> {
+    let mut lines = text.lines().map(str::trim);
+    let mut next_line = || lines.next().ok_or_else(|| invalid_data("unexpected end of file"));
+    let number = |s: &str| s.replace(',', ".").parse::<f32>().map_err(|_| invalid_data("invalid number"));
+
+    next_line()?;
+    let _type = next_line()?;
+    let symmetry = number(next_line()?)? as i32;
+    let c_count = number(next_line()?)? as usize;
+    let _c_step = next_line()?;
+    let g_count = number(next_line()?)? as usize;
+    let _g_step = next_line()?;
+    // Report number, luminaire name and number, file name, date and 9 dimensions
+    for _ in 0..14 {
+        next_line()?;
+    }
+    let _downward_flux_fraction = next_line()?;
+    let _light_output_ratio = next_line()?;
+    let conversion_factor = number(next_line()?)?;
+    let _tilt = next_line()?;
+    let lamp_sets = number(next_line()?)? as usize;
+    let mut flux = Vec::with_capacity(lamp_sets);
+    for field in 0..6 {
+        for _ in 0..lamp_sets {
+            let line = next_line()?;
+            if field == 2 {
+                flux.push(number(line)?);
+            }
+        }
+    }
+    // Direct ratios for the standard room indices
+    for _ in 0..10 {
+        next_line()?;
+    }
+    if c_count == 0 || g_count == 0 {
+        return Err(invalid_data("empty intensity table"));
+    }
+    let c_angles = (0..c_count).map(|_| next_line().and_then(number)).collect::<Result<Vec<f32>, Error>>()?;
+    let g_angles = (0..g_count).map(|_| next_line().and_then(number).map(f32::to_radians)).collect::<Result<Vec<f32>, Error>>()?;
+
+    let (first, count, symmetry) = match symmetry {
+        0 => (0, c_count, Symmetry::Full),
+        1 => (0, 1, Symmetry::Rotational),
+        2 => (0, c_count / 2 + 1, Symmetry::Bilateral(0.0)),
+        3 => (3 * c_count / 4, c_count / 2 + 1, Symmetry::Bilateral(PI * 0.5)),
+        4 => (0, c_count / 4 + 1, Symmetry::Quadrant),
+        _ => return Err(invalid_data("invalid symmetry indicator"))
+    };
+    let scale = conversion_factor.max(f32::EPSILON) * flux.first().copied().unwrap_or(1000.0).abs() / 1000.0;
+    let mut horizontal_angles = Vec::with_capacity(count);
+    let mut candela = Vec::with_capacity(count);
+    for i in 0..count {
+        let mut angle = c_angles[(first + i) % c_count];
+        // Planes stored across C0 continue past 360° so that the angles ascend
+        if let Some(previous) = horizontal_angles.last() {
+            while angle.to_radians() < *previous {
+                angle += 360.0;
+            }
+        }
+        horizontal_angles.push(angle.to_radians());
+        candela.push((0..g_count).map(|_| next_line().and_then(number).map(|c| c * scale)).collect::<Result<Vec<f32>, Error>>()?);
+    }
+    Ok(PhotometricProfile::new(g_angles, horizontal_angles, candela, symmetry))
+}
+
+/// Scales the spectrum of another radiator by the luminous intensity of a photometric profile.
+/// `rotation` orients the luminaire in the scene, `intensity` converts candela into scene units.
+pub struct PhotometricRadiator {
+    profile: PhotometricProfile,
+    rotation: Quat,
+    spectrum: Box<dyn Radiator>,
+    intensity: f32
+}
+
+impl PhotometricRadiator {
+    pub fn new(profile: PhotometricProfile, rotation: Quat, spectrum: Box<dyn Radiator>, intensity: f32) -> PhotometricRadiator {
+        PhotometricRadiator { profile, rotation, spectrum, intensity }
+    }
+}
+
+impl Radiator for PhotometricRadiator {
+    fn get_intensity(&self, direction: Vec3, wavelength: f32) -> f32 {
+        let local = self.rotation.inverse().mul_vec3(direction);
+        self.profile.get_candela(local) * self.spectrum.get_intensity(direction, wavelength) * self.intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::material::photometry::{parse_eulumdat, parse_ies};
+
+    const IES: &str = "IESNA:LM-63-2002
+[TEST] test
+[LUMINAIRE] test downlight
+TILT=NONE
+1 1000 2.0 3 3 1 2 0.1 0.1 0.0
+1.0 1.0 10
+0 45 90
+0 45 90
+100 50 0
+100 50 0
+200 100 0
+";
+
+    #[test]
+    fn ies_quadrant_symmetry() {
+        let profile = parse_ies(IES).unwrap();
+        assert_eq!(200.0, profile.get_candela(Vec3::new(0.0, 0.0, 1.0)));
+        assert_eq!(0.0, profile.get_candela(Vec3::new(0.0, 0.0, -1.0)));
+        let c90 = profile.get_candela(Vec3::new(0.0, 1.0, 1.0));
+        let c270 = profile.get_candela(Vec3::new(0.0, -1.0, 1.0));
+        let c180 = profile.get_candela(Vec3::new(-1.0, 0.0, 1.0));
+        assert!((c90 - 200.0).abs() < 1.0e-3);
+        assert!((c270 - 200.0).abs() < 1.0e-3);
+        assert!((c180 - 100.0).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn ies_with_tilt() {
+        let text = IES.replace("TILT=NONE", "TILT=INCLUDE\n1\n2\n0 90\n1.0 0.5");
+        let profile = parse_ies(&text).unwrap();
+        assert_eq!(200.0, profile.get_candela(Vec3::new(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn eulumdat_rotational() {
+        let mut lines = vec!["company", "1", "1", "4", "90", "3", "45"];
+        lines.extend(["report", "name", "number", "file", "date"]);
+        lines.extend(["100", "100", "50", "90", "90", "0", "0", "0", "0"]);
+        lines.extend(["100", "80", "1.0", "0", "1"]);
+        lines.extend(["1", "LED", "2000", "3000", "80", "20"]);
+        lines.extend(["0.5"; 10]);
+        lines.extend(["0", "90", "180", "270"]);
+        lines.extend(["0", "45", "90"]);
+        lines.extend(["300", "150", "0"]);
+        let profile = parse_eulumdat(&lines.join("\r\n")).unwrap();
+        assert_eq!(600.0, profile.get_candela(Vec3::new(0.0, 0.0, 1.0)));
+        let diagonal = profile.get_candela(Vec3::new(-1.0, -1.0, 2.0_f32.sqrt()));
+        assert!((diagonal - 300.0).abs() < 1.0);
+    }
+}
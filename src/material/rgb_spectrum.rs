@@ -0,0 +1,71 @@
+use glam::Vec3;
+
+/// Smits' basis spectra in 10 bins between 380 and 720 nm, see
+/// "An RGB to Spectrum Conversion for Reflectances" (1999)
+const BIN_START: f32 = 380.0;
+const BIN_WIDTH: f32 = 34.0;
+const WHITE: [f32; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const CYAN: [f32; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const MAGENTA: [f32; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const YELLOW: [f32; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const RED: [f32; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const GREEN: [f32; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const BLUE: [f32; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+fn sample(spectrum: &[f32; 10], wavelength: f32) -> f32 {
+    let position = ((wavelength - BIN_START) / BIN_WIDTH - 0.5).clamp(0.0, 9.0);
+    let index = (position as usize).min(8);
+    let remainder = position - index as f32;
+    spectrum[index] * (1.0 - remainder) + spectrum[index + 1] * remainder
+}
+
+/// Evaluates a smooth spectrum that reproduces the linear sRGB color `rgb` at `wavelength` (nm)
+pub fn rgb_to_spectrum(rgb: Vec3, wavelength: f32) -> f32 {
+    let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+    let s = |spectrum: &[f32; 10]| sample(spectrum, wavelength);
+    if r <= g && r <= b {
+        if g <= b {
+            r * s(&WHITE) + (g - r) * s(&CYAN) + (b - g) * s(&BLUE)
+        } else {
+            r * s(&WHITE) + (b - r) * s(&CYAN) + (g - b) * s(&GREEN)
+        }
+    } else if g <= r && g <= b {
+        if r <= b {
+            g * s(&WHITE) + (r - g) * s(&MAGENTA) + (b - r) * s(&BLUE)
+        } else {
+            g * s(&WHITE) + (b - g) * s(&MAGENTA) + (r - b) * s(&RED)
+        }
+    } else if r <= g {
+        b * s(&WHITE) + (r - b) * s(&YELLOW) + (g - r) * s(&GREEN)
+    } else {
+        b * s(&WHITE) + (g - b) * s(&YELLOW) + (r - g) * s(&RED)
+    }
+}
+
+/// Relative luminance of a linear sRGB color
+pub fn get_luminance(rgb: Vec3) -> f32 {
+    0.2126 * rgb.x + 0.7152 * rgb.y + 0.0722 * rgb.z
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::material::rgb_spectrum::rgb_to_spectrum;
+
+    #[test]
+    fn white_is_flat() {
+        for wavelength in [380.0, 450.0, 550.0, 650.0, 720.0] {
+            assert!((rgb_to_spectrum(Vec3::new(0.5, 0.5, 0.5), wavelength) - 0.5).abs() < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn primaries() {
+        let red = Vec3::new(1.0, 0.0, 0.0);
+        assert!(rgb_to_spectrum(red, 650.0) > 0.9);
+        assert!(rgb_to_spectrum(red, 530.0) < 0.1);
+        let blue = Vec3::new(0.0, 0.0, 1.0);
+        assert!(rgb_to_spectrum(blue, 440.0) > 0.9);
+        assert!(rgb_to_spectrum(blue, 600.0) < 0.1);
+    }
+}
@@ -0,0 +1,271 @@
+use std::f32::consts::PI;
+use glam::Vec3;
+use crate::geometry::util;
+use crate::material::cie::CIE_Y;
+use crate::material::environment::{Environment, ZENITH};
+use crate::material::material::Radiator;
+use crate::ptrandom;
+
+/// CIE daylight basis functions S0, S1 and S2 at 10nm intervals, starting at 300 nm.
+const DAYLIGHT_START: f32 = 300.0;
+const DAYLIGHT_STEP: f32 = 10.0;
+const DAYLIGHT_S0: [f32; 54] = [
+    0.04, 6.0, 29.6, 55.3, 57.3, 61.8, 61.5, 68.8, 63.4, 65.8, 94.8, 104.8, 105.9, 96.8, 113.9, 125.6, 125.5, 121.3,
+    121.3, 113.5, 113.1, 110.8, 106.5, 108.8, 105.3, 104.4, 100.0, 96.0, 95.1, 89.1, 90.5, 90.3, 88.4, 84.0, 85.1, 81.9,
+    82.6, 84.9, 81.3, 71.9, 74.3, 76.4, 63.3, 71.7, 77.0, 65.2, 47.7, 68.6, 65.0, 66.0, 61.0, 53.3, 58.9, 61.9
+];
+const DAYLIGHT_S1: [f32; 54] = [
+    0.02, 4.5, 22.4, 42.0, 40.6, 41.6, 38.0, 42.4, 38.5, 35.0, 43.4, 46.3, 43.9, 37.1, 36.7, 35.9, 32.6, 27.9,
+    24.3, 20.1, 16.2, 13.2, 8.6, 6.1, 4.2, 1.9, 0.0, -1.6, -3.5, -3.5, -5.8, -7.2, -8.6, -9.5, -10.9, -10.7,
+    -12.0, -14.0, -13.6, -12.0, -13.3, -12.9, -10.6, -11.6, -12.2, -10.2, -7.8, -11.2, -10.4, -10.6, -9.7, -8.3, -9.3, -9.8
+];
+const DAYLIGHT_S2: [f32; 54] = [
+    0.0, 2.0, 4.0, 8.5, 7.8, 6.7, 5.3, 6.1, 3.0, 1.2, -1.1, -0.5, -0.7, -1.2, -2.6, -2.9, -2.8, -2.6,
+    -2.6, -1.8, -1.5, -1.3, -1.2, -1.0, -0.5, -0.3, 0.0, 0.2, 0.5, 2.1, 3.2, 4.1, 4.7, 5.1, 6.7, 7.3,
+    8.6, 9.8, 10.2, 8.3, 9.6, 8.5, 7.0, 7.6, 8.0, 6.7, 5.2, 7.4, 6.8, 7.0, 6.4, 5.5, 6.1, 6.5
+];
+
+const LUMINOUS_EFFICACY: f32 = 683.0;
+const SUN_TEMPERATURE: f64 = 5778.0;
+/// Angular radius of the sun seen from earth, in radians
+const SUN_RADIUS: f32 = 0.004_65;
+
+fn sample_daylight(spectrum: &[f32; 54], wavelength: f32) -> f32 {
+    let position = ((wavelength - DAYLIGHT_START) / DAYLIGHT_STEP).clamp(0.0, 53.0);
+    let index = (position as usize).min(52);
+    let remainder = position - index as f32;
+    spectrum[index] * (1.0 - remainder) + spectrum[index + 1] * remainder
+}
+
+/// Integral of the luminous efficiency function over a daylight basis function
+fn get_luminance_integral(spectrum: &[f32; 54]) -> f32 {
+    CIE_Y.iter()
+        .enumerate()
+        .map(|(i, y)| y * sample_daylight(spectrum, 380.0 + i as f32 * 5.0) * 5.0)
+        .sum()
+}
+
+/// Perez et al. sky luminance distribution
+struct Perez {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32
+}
+
+impl Perez {
+    fn evaluate(&self, cos_theta: f32, gamma: f32) -> f32 {
+        (1.0 + self.a * (self.b / cos_theta.max(0.01)).exp()) * (1.0 + self.c * (self.d * gamma).exp() + self.e * gamma.cos() * gamma.cos())
+    }
+}
+
+/// Daylight sky by Preetham, Shirley and Smits, "A Practical Analytic Model for Daylight" (1999).
+/// Radiance is evaluated per wavelength in W/(m² sr nm) from the CIE daylight basis functions.
+/// `turbidity` ranges from 2 (very clear) to about 10 (hazy), `sun_direction` points towards the sun.
+pub struct PreethamSky {
+    turbidity: f32,
+    sun_direction: Vec3,
+    intensity: f32,
+    zenith: Vec3,
+    perez: [Perez; 3],
+    sun: Option<SunRadiator>,
+    /// Luminance integrals of the three daylight basis functions
+    basis_luminance: [f32; 3]
+}
+
+impl PreethamSky {
+    pub fn new(turbidity: f32, sun_direction: Vec3, intensity: f32) -> PreethamSky {
+        let t = turbidity;
+        let sun_direction = sun_direction.normalize();
+        let theta_s = sun_direction.dot(ZENITH).clamp(-1.0, 1.0).acos().min(PI * 0.5);
+        let theta_s2 = theta_s * theta_s;
+        let theta_s3 = theta_s2 * theta_s;
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let zenith_x = t * t * (0.00166 * theta_s3 - 0.00375 * theta_s2 + 0.00209 * theta_s)
+            + t * (-0.02903 * theta_s3 + 0.06377 * theta_s2 - 0.03202 * theta_s + 0.00394)
+            + (0.11693 * theta_s3 - 0.21196 * theta_s2 + 0.06052 * theta_s + 0.25886);
+        let zenith_y = t * t * (0.00275 * theta_s3 - 0.00610 * theta_s2 + 0.00317 * theta_s)
+            + t * (-0.04214 * theta_s3 + 0.08970 * theta_s2 - 0.04153 * theta_s + 0.00516)
+            + (0.15346 * theta_s3 - 0.26756 * theta_s2 + 0.06670 * theta_s + 0.26688);
+
+        let perez = [
+            Perez { a: 0.1787 * t - 1.4630, b: -0.3554 * t + 0.4275, c: -0.0227 * t + 5.3251, d: 0.1206 * t - 2.5771, e: -0.0670 * t + 0.3703 },
+            Perez { a: -0.0193 * t - 0.2592, b: -0.0665 * t + 0.0008, c: -0.0004 * t + 0.2125, d: -0.0641 * t - 0.8989, e: -0.0033 * t + 0.0452 },
+            Perez { a: -0.0167 * t - 0.2608, b: -0.0950 * t + 0.0092, c: -0.0079 * t + 0.2102, d: -0.0441 * t - 1.6537, e: -0.0109 * t + 0.0529 }
+        ];
+        // Store the zenith values already divided by the distribution at the zenith
+        let zenith = Vec3::new(zenith_luminance, zenith_x, zenith_y);
+        let zenith = Vec3::new(
+            zenith.x / perez[0].evaluate(1.0, theta_s),
+            zenith.y / perez[1].evaluate(1.0, theta_s),
+            zenith.z / perez[2].evaluate(1.0, theta_s)
+        );
+        let basis_luminance = [
+            get_luminance_integral(&DAYLIGHT_S0),
+            get_luminance_integral(&DAYLIGHT_S1),
+            get_luminance_integral(&DAYLIGHT_S2)
+        ];
+        PreethamSky { turbidity, sun_direction, intensity, zenith, perez, sun: None, basis_luminance }
+    }
+
+    /// Adds the disc of the sun, with the matching spectrum, to the sky
+    pub fn with_sun(mut self) -> PreethamSky {
+        self.sun = Some(SunRadiator::new(self.turbidity, self.sun_direction, self.intensity));
+        self
+    }
+
+    /// Luminance in kcd/m² and chromaticity of the sky in `direction`
+    fn get_xyy(&self, direction: Vec3) -> Option<Vec3> {
+        let cos_theta = direction.dot(ZENITH);
+        if cos_theta <= 0.0 {
+            return None;
+        }
+        let gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+        Some(Vec3::new(
+            self.zenith.x * self.perez[0].evaluate(cos_theta, gamma),
+            self.zenith.y * self.perez[1].evaluate(cos_theta, gamma),
+            self.zenith.z * self.perez[2].evaluate(cos_theta, gamma)
+        ))
+    }
+
+    fn get_sky_intensity(&self, direction: Vec3, wavelength: f32) -> f32 {
+        let xyy = match self.get_xyy(direction) {
+            Some(v) => v,
+            None => return 0.0
+        };
+        let (luminance, x, y) = (xyy.x, xyy.y, xyy.z);
+        let denominator = 0.0241 + 0.2562 * x - 0.7341 * y;
+        let m1 = (-1.3515 - 1.7703 * x + 5.9114 * y) / denominator;
+        let m2 = (0.0300 - 31.4424 * x + 30.0717 * y) / denominator;
+        let spectrum = sample_daylight(&DAYLIGHT_S0, wavelength)
+            + m1 * sample_daylight(&DAYLIGHT_S1, wavelength)
+            + m2 * sample_daylight(&DAYLIGHT_S2, wavelength);
+        let spectrum_luminance = self.basis_luminance[0] + m1 * self.basis_luminance[1] + m2 * self.basis_luminance[2];
+        (spectrum * luminance * 1000.0 / (LUMINOUS_EFFICACY * spectrum_luminance)).max(0.0) * self.intensity
+    }
+
+    fn get_sun_pdf(&self) -> f32 {
+        1.0 / (2.0 * PI * (1.0 - SUN_RADIUS.cos()))
+    }
+}
+
+impl Environment for PreethamSky {
+    fn get_intensity(&self, direction: Vec3, wavelength: f32) -> f32 {
+        let sky = self.get_sky_intensity(direction, wavelength);
+        match &self.sun {
+            Some(sun) if direction.dot(self.sun_direction) >= SUN_RADIUS.cos() => sky + sun.get_intensity(-direction, wavelength),
+            _ => sky
+        }
+    }
+
+    /// Picks the sun disc half of the time, since it is too small to be found by chance
+    fn sample_direction(&self) -> (Vec3, f32) {
+        if self.sun.is_none() {
+            return (ptrandom::get_sphere_vector(), 1.0 / (4.0 * PI));
+        }
+        let direction = if ptrandom::get_unit() < 0.5 {
+            let cos_theta = 1.0 - ptrandom::get_unit() * (1.0 - SUN_RADIUS.cos());
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = ptrandom::get_longitude();
+            util::rotate_towards(Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta), self.sun_direction)
+        } else {
+            ptrandom::get_sphere_vector()
+        };
+        (direction, self.get_pdf(direction))
+    }
+
+    fn get_pdf(&self, direction: Vec3) -> f32 {
+        let uniform = 1.0 / (4.0 * PI);
+        if self.sun.is_none() {
+            return uniform;
+        }
+        let in_sun = direction.dot(self.sun_direction) >= SUN_RADIUS.cos();
+        0.5 * uniform + if in_sun { 0.5 * self.get_sun_pdf() } else { 0.0 }
+    }
+}
+
+/// Spectral radiance of the sun disc in W/(m² sr nm) after passing through the atmosphere,
+/// attenuated by Rayleigh and aerosol scattering along the air mass of the sun's position.
+pub struct SunRadiator {
+    air_mass: f32,
+    /// Ångström turbidity coefficient
+    beta: f32,
+    intensity: f32
+}
+
+impl SunRadiator {
+    pub fn new(turbidity: f32, sun_direction: Vec3, intensity: f32) -> SunRadiator {
+        let theta = sun_direction.normalize().dot(ZENITH).clamp(-1.0, 1.0).acos();
+        let air_mass = if theta.to_degrees() >= 93.0 {
+            f32::INFINITY
+        } else {
+            1.0 / (theta.cos() + 0.15 * (93.885 - theta.to_degrees()).powf(-1.253))
+        };
+        SunRadiator { air_mass, beta: 0.04608 * turbidity - 0.04586, intensity }
+    }
+
+    fn get_extraterrestrial_radiance(wavelength: f32) -> f32 {
+        let l = wavelength as f64 * 1.0e-9;
+        let h = 6.62607015e-34;
+        let c = 299792458.0;
+        let k = 1.380649e-23;
+        let radiance = 2.0 * h * c * c / (l.powi(5) * ((h * c / (l * k * SUN_TEMPERATURE)).exp() - 1.0));
+        (radiance * 1.0e-9) as f32
+    }
+}
+
+impl Radiator for SunRadiator {
+    fn get_intensity(&self, _direction: Vec3, wavelength: f32) -> f32 {
+        let micrometers = wavelength * 1.0e-3;
+        let rayleigh = (-0.008735 * micrometers.powf(-4.08) * self.air_mass).exp();
+        let aerosol = (-self.beta * micrometers.powf(-1.3) * self.air_mass).exp();
+        SunRadiator::get_extraterrestrial_radiance(wavelength) * rayleigh * aerosol * self.intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::material::environment::Environment;
+    use crate::material::material::Radiator;
+    use crate::material::sky::{PreethamSky, SunRadiator};
+
+    #[test]
+    fn zenith_luminance_is_plausible() {
+        let sky = PreethamSky::new(3.0, Vec3::new(1.0, 0.0, -1.0), 1.0);
+        // Clear sky with the sun at 45°, the zenith is a few kcd/m²
+        let xyy = sky.get_xyy(Vec3::new(0.0, 0.0, -1.0)).unwrap();
+        assert!(xyy.x > 2.0 && xyy.x < 15.0, "luminance {}", xyy.x);
+        assert!(xyy.y > 0.2 && xyy.y < 0.35 && xyy.z > 0.2 && xyy.z < 0.4);
+    }
+
+    #[test]
+    fn sky_is_blue_and_ground_is_dark() {
+        let sky = PreethamSky::new(2.5, Vec3::new(1.0, 0.0, -0.5), 1.0);
+        let up = Vec3::new(0.0, 0.0, -1.0);
+        assert!(sky.get_intensity(up, 450.0) > sky.get_intensity(up, 650.0));
+        assert_eq!(0.0, sky.get_intensity(Vec3::new(0.0, 0.0, 1.0), 550.0));
+    }
+
+    #[test]
+    fn sunset_is_red() {
+        let noon = SunRadiator::new(3.0, Vec3::new(0.0, 0.0, -1.0), 1.0);
+        let sunset = SunRadiator::new(3.0, Vec3::new(1.0, 0.0, -0.02), 1.0);
+        let ratio = |sun: &SunRadiator| sun.get_intensity(Vec3::Z, 650.0) / sun.get_intensity(Vec3::Z, 450.0);
+        assert!(ratio(&sunset) > 2.0 * ratio(&noon));
+    }
+
+    #[test]
+    fn sun_disc_is_sampled() {
+        let sun_direction = Vec3::new(0.3, 0.2, -1.0).normalize();
+        let sky = PreethamSky::new(3.0, sun_direction, 1.0).with_sun();
+        let hits = (0..1000)
+            .map(|_| sky.sample_direction())
+            .filter(|(d, pdf)| d.dot(sun_direction) > 0.9999 && *pdf > 1000.0)
+            .count();
+        assert!(hits > 400 && hits < 600);
+    }
+}
@@ -1,4 +1,6 @@
+use glam::Vec3;
 use crate::material::material::Radiator;
+use crate::material::rgb_spectrum;
 
 pub struct SpectrumRadiator {
     min_wavelength: f32,
@@ -12,7 +14,7 @@ impl SpectrumRadiator {
 }
 
 impl Radiator for SpectrumRadiator {
-    fn get_intensity(&self, wavelength: f32) -> f32 {
+    fn get_intensity(&self, _direction: Vec3, wavelength: f32) -> f32 {
         if wavelength >= self.min_wavelength && wavelength < self.max_wavelength {
             1.0
         } else {
@@ -20,3 +22,21 @@ impl Radiator for SpectrumRadiator {
         }
     }
 }
+
+/// Emits a linear sRGB color, e.g. the emissive factor of an imported material
+pub struct RgbRadiator {
+    color: Vec3,
+    intensity: f32
+}
+
+impl RgbRadiator {
+    pub fn new(color: Vec3, intensity: f32) -> RgbRadiator {
+        RgbRadiator { color: color.max(Vec3::ZERO), intensity }
+    }
+}
+
+impl Radiator for RgbRadiator {
+    fn get_intensity(&self, _direction: Vec3, wavelength: f32) -> f32 {
+        rgb_spectrum::rgb_to_spectrum(self.color, wavelength) * self.intensity
+    }
+}
@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::path::Path;
+use glam::Vec3;
+use crate::material::material::Radiator;
+
+/// Spectral power distribution given as samples, linearly interpolated and zero outside of them
+pub struct TabulatedRadiator {
+    wavelengths: Vec<f32>,
+    values: Vec<f32>,
+    intensity: f32
+}
+
+impl TabulatedRadiator {
+    /// `samples` are pairs of wavelength in nm and relative power, sorted by wavelength
+    pub fn new(samples: Vec<(f32, f32)>, intensity: f32) -> TabulatedRadiator {
+        let (wavelengths, values) = samples.into_iter().unzip();
+        TabulatedRadiator { wavelengths, values, intensity }
+    }
+
+    /// Reads a spectrum with a wavelength and a value on each line, separated by whitespace or a
+    /// comma. Lines starting with `#` are ignored.
+    pub fn read(path: &Path, intensity: f32) -> std::io::Result<TabulatedRadiator> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut samples = vec![];
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = || Error::new(ErrorKind::InvalidData, format!("line {}: expected wavelength and value", index + 1));
+            let columns: Vec<f32> = line.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.parse::<f32>().map_err(|_| invalid()))
+                .collect::<Result<_, _>>()?;
+            if columns.len() != 2 {
+                return Err(invalid());
+            }
+            samples.push((columns[0], columns[1]));
+        }
+        if samples.len() < 2 || samples.windows(2).any(|w| w[1].0 <= w[0].0) {
+            return Err(Error::new(ErrorKind::InvalidData, "spectrum needs at least two ascending wavelengths"));
+        }
+        Ok(TabulatedRadiator::new(samples, intensity))
+    }
+}
+
+impl Radiator for TabulatedRadiator {
+    fn get_intensity(&self, _direction: Vec3, wavelength: f32) -> f32 {
+        let last = self.wavelengths.len() - 1;
+        if wavelength < self.wavelengths[0] || wavelength > self.wavelengths[last] {
+            return 0.0;
+        }
+        let index = (self.wavelengths.partition_point(|w| *w <= wavelength).max(1) - 1).min(last.max(1) - 1);
+        if index == last {
+            return self.values[last] * self.intensity;
+        }
+        let width = self.wavelengths[index + 1] - self.wavelengths[index];
+        let t = ((wavelength - self.wavelengths[index]) / width).clamp(0.0, 1.0);
+        (self.values[index] * (1.0 - t) + self.values[index + 1] * t) * self.intensity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::material::material::Radiator;
+    use crate::material::tabulated_radiator::TabulatedRadiator;
+
+    #[test]
+    fn interpolates_samples() {
+        let radiator = TabulatedRadiator::new(vec![(400.0, 1.0), (500.0, 3.0), (600.0, 1.0)], 2.0);
+        assert_eq!(4.0, radiator.get_intensity(Vec3::Z, 450.0));
+        assert_eq!(2.0, radiator.get_intensity(Vec3::Z, 600.0));
+        assert_eq!(0.0, radiator.get_intensity(Vec3::Z, 650.0));
+    }
+}
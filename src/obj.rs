@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use glam::{Vec2, Vec3};
+use crate::entity::Entity;
+use crate::geometry::mesh::{Mesh, Triangle};
+use crate::material::diffuse::DiffuseGrayMaterial;
+use crate::material::dispersion::Dispersion;
+use crate::material::glass::DielectricMaterial;
+use crate::material::glossy::GlossyMaterial;
+use crate::material::material::Material;
+use crate::material::rgb_spectrum;
+
+/// Creates the material for a `usemtl` name, used in place of the conversion from the MTL file
+pub type MaterialTable = HashMap<String, Box<dyn Fn() -> Box<dyn Material>>>;
+
+/// The parts of an MTL material that have a counterpart in rtrace
+#[derive(Clone, Debug, PartialEq)]
+pub struct MtlMaterial {
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub specular_exponent: f32,
+    pub refraction_index: f32,
+    pub opacity: f32
+}
+
+impl Default for MtlMaterial {
+    fn default() -> MtlMaterial {
+        MtlMaterial { diffuse: Vec3::splat(0.8), specular: Vec3::ZERO, specular_exponent: 0.0, refraction_index: 1.0, opacity: 1.0 }
+    }
+}
+
+impl MtlMaterial {
+    /// Best effort approximation: transparent materials become dielectrics, materials with a
+    /// specular color glossy and everything else gray diffuse by the luminance of `Kd`
+    pub fn to_material(&self) -> Box<dyn Material> {
+        if self.opacity < 1.0 {
+            let ior = if self.refraction_index > 1.0 { self.refraction_index } else { 1.5 };
+            return Box::new(DielectricMaterial::new(Dispersion::Constant(ior)));
+        }
+        let diffuse = rgb_spectrum::get_luminance(self.diffuse).clamp(0.0, 1.0);
+        let specular = rgb_spectrum::get_luminance(self.specular).clamp(0.0, 1.0);
+        let base = Box::new(DiffuseGrayMaterial::new((diffuse + specular).min(1.0)));
+        if specular > 0.0 {
+            Box::new(GlossyMaterial::new(self.get_glossiness(diffuse, specular), base))
+        } else {
+            base
+        }
+    }
+
+    /// Share of the diffuse direction in a glossy reflection, less for a larger specular part and
+    /// a sharper highlight. The Phong exponent `Ns` is turned into a roughness like
+    /// `sqrt(2 / (Ns + 2))`, so an exponent of 0 gives no mirror component at all.
+    fn get_glossiness(&self, diffuse: f32, specular: f32) -> f32 {
+        let roughness = (2.0 / (self.specular_exponent.max(0.0) + 2.0)).sqrt();
+        1.0 - specular / (diffuse + specular) * (1.0 - roughness)
+    }
+}
+
+/// Triangles of one group that share a material
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    pub triangles: Vec<Triangle>
+}
+
+pub struct ObjModel {
+    pub groups: Vec<ObjGroup>,
+    pub materials: HashMap<String, MtlMaterial>
+}
+
+impl ObjModel {
+    /// One entity per group and material. Names in `table` take precedence over the MTL
+    /// definitions, unknown names fall back to a gray diffuse material.
+    pub fn into_entities(self, table: &MaterialTable) -> Vec<Entity> {
+        let materials = self.materials;
+        self.groups.into_iter()
+            .map(|group| {
+                let material = match group.material.as_ref() {
+                    Some(name) => match (table.get(name), materials.get(name)) {
+                        (Some(create), _) => create(),
+                        (None, Some(mtl)) => mtl.to_material(),
+                        (None, None) => MtlMaterial::default().to_material()
+                    },
+                    None => MtlMaterial::default().to_material()
+                };
+                Entity::dark(Box::new(Mesh::new(group.triangles)), material)
+            })
+            .collect()
+    }
+}
+
+/// Reads an OBJ file together with the MTL files it references, relative to its directory
+pub fn read_obj(path: &Path) -> std::io::Result<ObjModel> {
+    let source = fs::read_to_string(path)?;
+    let mut model = parse_obj(&source)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    for library in get_material_libraries(&source) {
+        model.materials.extend(parse_mtl(&fs::read_to_string(directory.join(library))?)?);
+    }
+    Ok(model)
+}
+
+fn get_material_libraries(source: &str) -> Vec<String> {
+    source.lines()
+        .filter_map(|line| line.trim().strip_prefix("mtllib "))
+        .flat_map(|names| names.split_whitespace().map(String::from))
+        .collect()
+}
+
+fn invalid(line: usize, message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("line {}: {}", line, message))
+}
+
+fn parse_floats(line: usize, values: &[&str], count: usize) -> std::io::Result<Vec<f32>> {
+    if values.len() < count {
+        return Err(invalid(line, &format!("expected {} numbers", count)));
+    }
+    values[..count].iter()
+        .map(|s| s.parse::<f32>().map_err(|_| invalid(line, &format!("invalid number {}", s))))
+        .collect()
+}
+
+/// Resolves a one based or negative, i.e. relative to the end, index into a list of `count`
+fn resolve_index(line: usize, index: &str, count: usize) -> std::io::Result<usize> {
+    let index = index.parse::<i64>().map_err(|_| invalid(line, &format!("invalid index {}", index)))?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(invalid(line, &format!("index {} out of range", index)));
+    }
+    Ok(resolved as usize)
+}
+
+/// Parses the geometry of an OBJ file, polygons are triangulated as fans. `mtllib` statements
+/// are ignored, see `read_obj`.
+pub fn parse_obj(source: &str) -> std::io::Result<ObjModel> {
+    let mut positions: Vec<Vec3> = vec![];
+    let mut uvs: Vec<Vec2> = vec![];
+    let mut normals: Vec<Vec3> = vec![];
+    let mut groups = vec![ObjGroup { name: String::new(), material: None, triangles: vec![] }];
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue
+        };
+        let values: Vec<&str> = tokens.collect();
+        match keyword {
+            "v" => {
+                let v = parse_floats(line_number, &values, 3)?;
+                positions.push(Vec3::new(v[0], v[1], v[2]));
+            }
+            "vt" => {
+                let v = parse_floats(line_number, &values, 1)?;
+                let t = values.get(1).and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+                uvs.push(Vec2::new(v[0], t));
+            }
+            "vn" => {
+                let v = parse_floats(line_number, &values, 3)?;
+                normals.push(Vec3::new(v[0], v[1], v[2]).normalize_or_zero());
+            }
+            "g" | "o" => {
+                let material = groups.last().unwrap().material.clone();
+                groups.push(ObjGroup { name: values.join(" "), material, triangles: vec![] });
+            }
+            "usemtl" => {
+                let name = groups.last().unwrap().name.clone();
+                groups.push(ObjGroup { name, material: Some(values.join(" ")), triangles: vec![] });
+            }
+            "f" => {
+                if values.len() < 3 {
+                    return Err(invalid(line_number, "faces need at least three vertices"));
+                }
+                let mut corners = vec![];
+                for value in &values {
+                    let mut parts = value.split('/');
+                    let position = resolve_index(line_number, parts.next().unwrap(), positions.len())?;
+                    let uv = match parts.next() {
+                        Some(s) if !s.is_empty() => Some(resolve_index(line_number, s, uvs.len())?),
+                        _ => None
+                    };
+                    let normal = match parts.next() {
+                        Some(s) if !s.is_empty() => Some(resolve_index(line_number, s, normals.len())?),
+                        _ => None
+                    };
+                    corners.push((position, uv, normal));
+                }
+                let triangles = &mut groups.last_mut().unwrap().triangles;
+                for i in 1..corners.len() - 1 {
+                    let [a, b, c] = [corners[0], corners[i], corners[i + 1]];
+                    let vertices = [positions[a.0], positions[b.0], positions[c.0]];
+                    let mut triangle = match (a.2, b.2, c.2) {
+                        (Some(na), Some(nb), Some(nc)) => Triangle::with_normals(vertices[0], vertices[1], vertices[2], [normals[na], normals[nb], normals[nc]]),
+                        _ => Triangle::new(vertices[0], vertices[1], vertices[2])
+                    };
+                    if let (Some(ta), Some(tb), Some(tc)) = (a.1, b.1, c.1) {
+                        triangle = triangle.with_uvs([uvs[ta], uvs[tb], uvs[tc]]);
+                    }
+                    triangles.push(triangle);
+                }
+            }
+            _ => {}
+        }
+    }
+    groups.retain(|g| !g.triangles.is_empty());
+    Ok(ObjModel { groups, materials: HashMap::new() })
+}
+
+/// Parses the `Kd`, `Ks`, `Ns`, `Ni`, `d` and `Tr` statements of an MTL file
+pub fn parse_mtl(source: &str) -> std::io::Result<HashMap<String, MtlMaterial>> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue
+        };
+        let values: Vec<&str> = tokens.collect();
+        if keyword == "newmtl" {
+            materials.extend(current.take());
+            current = Some((values.join(" "), MtlMaterial::default()));
+            continue;
+        }
+        let material = match current.as_mut() {
+            Some((_, material)) => material,
+            None => continue
+        };
+        match keyword {
+            "Kd" | "Ks" => {
+                let v = parse_floats(line_number, &values, 3)?;
+                let color = Vec3::new(v[0], v[1], v[2]);
+                if keyword == "Kd" { material.diffuse = color } else { material.specular = color }
+            }
+            "Ns" => material.specular_exponent = parse_floats(line_number, &values, 1)?[0],
+            "Ni" => material.refraction_index = parse_floats(line_number, &values, 1)?[0],
+            "d" => material.opacity = parse_floats(line_number, &values, 1)?[0],
+            "Tr" => material.opacity = 1.0 - parse_floats(line_number, &values, 1)?[0],
+            _ => {}
+        }
+    }
+    materials.extend(current);
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use crate::geometry::ray::Ray;
+    use crate::geometry::surface::Surface;
+    use crate::obj;
+
+    const QUAD: &str = "
+        mtllib quad.mtl
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        vt 0 0
+        vt 1 0
+        vt 1 1
+        vt 0 1
+        vn 0 0 -1
+        g front
+        usemtl red
+        f 1/1/1 2/2/1 3/3/1 4/4/1
+        g back
+        f -4 -1 -2
+    ";
+
+    #[test]
+    fn polygons_and_groups() {
+        let model = obj::parse_obj(QUAD).unwrap();
+        assert_eq!(2, model.groups.len());
+        assert_eq!("front", model.groups[0].name);
+        assert_eq!(Some("red".to_string()), model.groups[0].material);
+        assert_eq!(2, model.groups[0].triangles.len());
+        assert_eq!(1, model.groups[1].triangles.len());
+        assert_eq!(Some("red".to_string()), model.groups[1].material);
+
+        let ray = Ray::new(Vec3::new(0.25, 0.75, -1.0), Vec3::new(0.0, 0.0, 1.0), 550.0, 1.0);
+        let hit = model.groups[0].triangles.iter().find_map(|t| t.intersect(&ray)).unwrap();
+        assert!((hit.uv - glam::Vec2::new(0.25, 0.75)).length() < 1.0e-5);
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), hit.normal);
+        assert_eq!(vec!["quad.mtl".to_string()], obj::get_material_libraries(QUAD));
+    }
+
+    #[test]
+    fn invalid_indices() {
+        assert!(obj::parse_obj("v 0 0 0\nv 1 0 0\nf 1 2 3").is_err());
+        assert!(obj::parse_obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 -4").is_err());
+    }
+
+    #[test]
+    fn materials() {
+        let materials = obj::parse_mtl("
+            newmtl red
+            Kd 0.8 0.1 0.1
+            Ks 0.2 0.2 0.2
+            Ns 100
+            newmtl glass
+            Ni 1.45
+            d 0.1
+        ").unwrap();
+        assert_eq!(2, materials.len());
+        assert_eq!(Vec3::new(0.8, 0.1, 0.1), materials["red"].diffuse);
+        assert_eq!(100.0, materials["red"].specular_exponent);
+        assert_eq!(1.45, materials["glass"].refraction_index);
+        assert_eq!(Some(1.45), materials["glass"].to_material().get_refraction_index(550.0));
+        assert_eq!(None, materials["red"].to_material().get_refraction_index(550.0));
+        let dull = obj::MtlMaterial { specular_exponent: 0.0, ..materials["red"].clone() };
+        assert_eq!(1.0, dull.get_glossiness(0.3, 0.2));
+        assert!(materials["red"].get_glossiness(0.3, 0.2) < 0.7);
+    }
+}
@@ -24,3 +24,10 @@ pub fn get_hemisphere_vector() -> Vec3 {
     Vec3::new(phi.cos() * r, phi.sin() * r, (1.0 - rq).sqrt())
 }
 
+
+pub fn get_sphere_vector() -> Vec3 {
+    let phi = get_longitude();
+    let z = get_bi_unit();
+    let r = (1.0 - z * z).sqrt();
+    Vec3::new(phi.cos() * r, phi.sin() * r, z)
+}
@@ -1,6 +1,9 @@
-use crate::entity::Entity::{DARK, LUMINOUS};
+use crate::entity::Entity;
+use crate::geometry::ray;
 use crate::geometry::ray::Ray;
 use crate::scene::Scene;
+use std::f32::consts::PI;
+use glam::Vec3;
 use super::ptrandom;
 
 pub struct Photon {
@@ -41,30 +44,149 @@ impl RenderIterator<'_> {
 
     fn render_ray(&self, ray: Ray) -> f32 {
         let mut continue_chance = 1.0;
-        let mut intensity = 1.0;
+        let mut intensity = ray.strength;
+        let mut radiance = 0.0;
+        // Density of the last diffuse bounce, used to weight light that was also sampled explicitly
+        let mut diffuse_pdf: Option<f32> = None;
         let mut current_ray = ray;
+        // Dielectrics the ray is currently inside of, in the order it entered them
+        let mut media: Vec<&Entity> = vec![];
         loop {
             let intersection = self.scene.intersect(&current_ray);
-            if let Some(i) = intersection {
-                if let LUMINOUS(surface, radiator) = i.0 {
-                    return intensity * radiator.get_intensity(current_ray.wavelength);
+            if let Some((entity, mut hit)) = intersection {
+                if let Some(radiator) = &entity.radiator {
+                    radiance += intensity * radiator.get_intensity(-current_ray.direction, current_ray.wavelength);
                 }
-                else if let DARK(surface, material) = i.0 {
-                    current_ray = material.get_next_ray(current_ray, i.1);
-                    intensity = intensity * current_ray.strength;
+                let material = match &entity.material {
+                    Some(m) => m,
+                    None => return radiance
+                };
+                let normal = hit.get_facing_normal();
+                let geometric_normal = hit.geometric_normal;
+                diffuse_pdf = None;
+                if material.get_refraction_index(current_ray.wavelength).is_some() {
+                    let exterior = get_medium(&media, entity);
+                    if exterior.is_some_and(|e| get_priority(e) > material.get_priority()) {
+                        // The surface lies inside of a medium with a higher priority, which takes its place
+                        toggle_medium(&mut media, entity);
+                        current_ray.t_min = hit.t;
+                        continue;
+                    }
+                    hit.exterior_ior = exterior
+                        .and_then(|e| e.material.as_ref())
+                        .and_then(|m| m.get_refraction_index(current_ray.wavelength))
+                        .unwrap_or(1.0);
+                    current_ray = material.get_next_ray(current_ray, hit);
+                    if current_ray.direction.dot(normal) < 0.0 {
+                        toggle_medium(&mut media, entity);
+                    }
+                } else if let Some(reflectance) = material.get_diffuse_reflectance(current_ray.wavelength) {
+                    // Shadow rays leave from just above the actual surface, not the shading normal
+                    let outside = if hit.front_face { geometric_normal } else { geometric_normal * -1.0 };
+                    let origin = ray::offset_origin(hit.position, outside);
+                    let incoming = self.sample_environment(origin, normal, current_ray.wavelength)
+                        + self.sample_lights(origin, normal, current_ray.wavelength);
+                    radiance += intensity * reflectance * incoming;
+                    current_ray = material.get_next_ray(current_ray, hit);
+                    diffuse_pdf = Some(current_ray.direction.dot(normal).max(0.0) / PI);
+                } else {
+                    current_ray = material.get_next_ray(current_ray, hit);
                 }
-                current_ray.position = current_ray.position + current_ray.direction * 0.0001;
+                intensity *= current_ray.strength;
+                current_ray = current_ray.with_offset(geometric_normal);
                 continue_chance *= 0.96;
                 if ptrandom::get_unit() * 0.85 > continue_chance * (1.0 - (intensity * -20.0).exp()) {
                     break;
                 }
             }
             else {
-                return 0.0;
+                return radiance + intensity * self.get_environment_intensity(&current_ray, diffuse_pdf);
             }
         }
-        return 0.0;
+        radiance
     }
+
+    /// Radiance of the environment seen by a ray that left the scene
+    fn get_environment_intensity(&self, ray: &Ray, diffuse_pdf: Option<f32>) -> f32 {
+        match &self.scene.environment {
+            None => 0.0,
+            Some(environment) => {
+                let weight = match diffuse_pdf {
+                    Some(pdf) => power_heuristic(pdf, environment.get_pdf(ray.direction)),
+                    None => 1.0
+                };
+                weight * environment.get_intensity(ray.direction, ray.wavelength)
+            }
+        }
+    }
+
+    /// Estimates the light of the environment reflected by a white Lambertian surface
+    fn sample_environment(&self, origin: Vec3, normal: Vec3, wavelength: f32) -> f32 {
+        let environment = match &self.scene.environment {
+            None => return 0.0,
+            Some(e) => e
+        };
+        let (direction, pdf) = environment.sample_direction();
+        let cos = direction.dot(normal);
+        if cos <= 0.0 || pdf <= 0.0 {
+            return 0.0;
+        }
+        let shadow_ray = Ray::new(origin, direction, wavelength, 1.0);
+        if self.scene.intersect(&shadow_ray).is_some() {
+            return 0.0;
+        }
+        let weight = power_heuristic(pdf, cos / PI);
+        environment.get_intensity(direction, wavelength) * cos / PI / pdf * weight
+    }
+
+    /// Light of all point-like light sources reflected by a white Lambertian surface
+    fn sample_lights(&self, origin: Vec3, normal: Vec3, wavelength: f32) -> f32 {
+        let mut sum = 0.0;
+        for light in &self.scene.lights {
+            let sample = match light.sample(origin, wavelength) {
+                Some(s) => s,
+                None => continue
+            };
+            let cos = sample.direction.dot(normal);
+            if cos <= 0.0 || sample.intensity <= 0.0 {
+                continue;
+            }
+            let shadow_ray = Ray::new(origin, sample.direction, wavelength, 1.0).with_interval(0.0, sample.distance);
+            if self.scene.intersect(&shadow_ray).is_none() {
+                sum += sample.intensity * cos / PI;
+            }
+        }
+        sum
+    }
+}
+
+fn get_priority(entity: &Entity) -> i32 {
+    entity.material.as_ref().map_or(0, |m| m.get_priority())
+}
+
+/// The medium surrounding `surface`, which is the one with the highest priority of all media the
+/// ray is in apart from `surface` itself. Of equal priorities the innermost one wins.
+fn get_medium<'a>(media: &[&'a Entity], surface: &Entity) -> Option<&'a Entity> {
+    media.iter()
+        .filter(|m| !std::ptr::eq(**m, surface))
+        .fold(None, |best: Option<&'a Entity>, m| match best {
+            Some(b) if get_priority(b) > get_priority(m) => Some(b),
+            _ => Some(*m)
+        })
+}
+
+/// Enters the medium of `entity` or leaves it if the ray is already inside
+fn toggle_medium<'a>(media: &mut Vec<&'a Entity>, entity: &'a Entity) {
+    match media.iter().position(|m| std::ptr::eq(*m, entity)) {
+        Some(index) => { media.remove(index); }
+        None => media.push(entity)
+    }
+}
+
+/// Multiple importance sampling weight of a sample drawn with density `pdf`
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let p = pdf * pdf;
+    p / (p + other_pdf * other_pdf)
 }
 
 